@@ -0,0 +1,225 @@
+use chrono::{Duration, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashSet};
+use std::fmt;
+
+use crate::card::{Factors, Rating, Status};
+use crate::history::{self, Review};
+use crate::session::{Session, TABLES};
+
+const XP_GOOD: u64 = 10;
+const XP_BAD: u64 = 1;
+const IN_A_ROW: &[usize] = &[10, 25, 50, 100];
+const STREAKS: &[usize] = &[3, 7, 30, 100];
+/// A sprint is this many good answers in a row, in one session
+const SPRINT_LENGTH: usize = 20;
+const SPRINT_AVERAGE_MS: u64 = 2000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(tag = "badge", rename_all = "snake_case")]
+pub enum Badge {
+    /// Every fact with `table` in it is learned
+    Table {
+        table: u8,
+    },
+    InARow {
+        count: usize,
+    },
+    Streak {
+        days: usize,
+    },
+    Sprint,
+}
+
+impl fmt::Display for Badge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Badge::Table { table } => write!(f, "All {}s learned", table),
+            Badge::InARow { count } => write!(f, "{} in a row", count),
+            Badge::Streak { days } => write!(f, "{} days streak", days),
+            Badge::Sprint => write!(
+                f,
+                "{} in a row under {}s on average",
+                SPRINT_LENGTH,
+                SPRINT_AVERAGE_MS / 1000
+            ),
+        }
+    }
+}
+
+/// Everything here is derived from the review history
+#[derive(Debug, PartialEq, Eq)]
+pub struct Progress {
+    pub xp: u64,
+    /// Days of practice in a row, up to today
+    pub streak: usize,
+    pub badges: Vec<Badge>,
+}
+
+pub fn xp(history: &[Review]) -> u64 {
+    history
+        .iter()
+        .map(|review| match review.rating {
+            Rating::Good => XP_GOOD,
+            Rating::Bad => XP_BAD,
+        })
+        .sum()
+}
+
+fn longest_run(history: &[Review]) -> usize {
+    history
+        .split(|review| review.rating == Rating::Bad)
+        .map(|run| run.len())
+        .max()
+        .unwrap_or(0)
+}
+
+fn longest_streak(dates: &BTreeSet<NaiveDate>) -> usize {
+    let mut longest = 0;
+    let mut streak = 0;
+    let mut previous: Option<NaiveDate> = None;
+    for &date in dates {
+        streak = match previous {
+            Some(previous) if previous + Duration::days(1) == date => streak + 1,
+            _ => 1,
+        };
+        longest = longest.max(streak);
+        previous = Some(date);
+    }
+    longest
+}
+
+fn sprinted(history: &[Review]) -> bool {
+    history::sessions(history)
+        .into_iter()
+        .flat_map(|session| session.windows(SPRINT_LENGTH))
+        .any(|run| {
+            run.iter().all(|review| review.rating == Rating::Good)
+                && run
+                    .iter()
+                    .map(|review| review.duration)
+                    .sum::<Option<u64>>()
+                    .is_some_and(|total| total < SPRINT_AVERAGE_MS * SPRINT_LENGTH as u64)
+        })
+}
+
+pub fn badges(history: &[Review]) -> Vec<Badge> {
+    let learned: HashSet<Factors> = Session::replay(history.to_vec())
+        .cards
+        .into_iter()
+        .filter(|card| matches!(card.status, Status::Learned(_)))
+        .filter_map(|card| card.value.factors())
+        .collect();
+    let tables = TABLES
+        .filter(|&table| {
+            TABLES.clone().all(|n| {
+                learned.contains(&Factors(table, n)) && learned.contains(&Factors(n, table))
+            })
+        })
+        .map(|table| Badge::Table { table });
+
+    let run = longest_run(history);
+    let in_a_row = IN_A_ROW
+        .iter()
+        .filter(|&&count| run >= count)
+        .map(|&count| Badge::InARow { count });
+
+    let dates: BTreeSet<NaiveDate> = history.iter().map(Review::date).collect();
+    let streak = longest_streak(&dates);
+    let streaks = STREAKS
+        .iter()
+        .filter(|&&days| streak >= days)
+        .map(|&days| Badge::Streak { days });
+
+    let sprint = Some(Badge::Sprint).filter(|_| sprinted(history));
+
+    tables
+        .chain(in_a_row)
+        .chain(streaks)
+        .chain(sprint)
+        .collect()
+}
+
+impl Progress {
+    pub fn new(history: &[Review], today: NaiveDate) -> Progress {
+        let dates: BTreeSet<NaiveDate> = history.iter().map(Review::date).collect();
+        Progress {
+            xp: xp(history),
+            streak: history::streak(&dates, today),
+            badges: badges(history),
+        }
+    }
+
+    /// Stores the badges in the session. Returns the ones it did not have
+    /// yet.
+    pub fn unlock(&self, session: &mut Session) -> Vec<Badge> {
+        let unlocked = self
+            .badges
+            .iter()
+            .filter(|badge| !session.achievements.contains(badge))
+            .copied()
+            .collect();
+        session.achievements = self.badges.clone();
+        unlocked
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::card::Card;
+
+    const DAY: u64 = 24 * 60 * 60;
+
+    fn a_review(value: Factors, rating: Rating, time: u64, duration: u64) -> Review {
+        Review {
+            value: value.into(),
+            rating,
+            time,
+            duration: Some(duration),
+            input: None,
+        }
+    }
+
+    #[test]
+    fn achievements_from_history() {
+        let mut history: Vec<Review> = (2..10)
+            .flat_map(|x| (2..10).map(move |y| Factors(x, y)))
+            .enumerate()
+            .map(|(i, value)| a_review(value, Rating::Good, i as u64, 1000))
+            .collect();
+        history.push(a_review(Factors(7, 8), Rating::Bad, 100, 1000));
+        history.push(a_review(Factors(7, 8), Rating::Good, 2 * DAY, 1000));
+
+        let badges = badges(&history);
+        assert!(!badges.contains(&Badge::Table { table: 7 }));
+        assert!(badges.contains(&Badge::Table { table: 6 }));
+        assert!(badges.contains(&Badge::InARow { count: 50 }));
+        assert!(!badges.contains(&Badge::InARow { count: 100 }));
+        assert!(badges.contains(&Badge::Sprint));
+        assert!(!badges
+            .iter()
+            .any(|badge| matches!(badge, Badge::Streak { .. })));
+        assert_eq!(xp(&history), 65 * XP_GOOD + XP_BAD);
+    }
+
+    #[test]
+    fn achievements_longest_streak() {
+        let day = |d| NaiveDate::from_ymd_opt(2024, 1, d).unwrap();
+        let dates = [1, 2, 3, 5, 6].iter().map(|&d| day(d)).collect();
+        assert_eq!(longest_streak(&dates), 3);
+        assert_eq!(longest_streak(&BTreeSet::new()), 0);
+    }
+
+    #[test]
+    fn achievements_unlock() {
+        let mut session = Session::from(vec![Card::new(7, 8)]);
+        let progress = Progress {
+            xp: 0,
+            streak: 0,
+            badges: vec![Badge::Sprint],
+        };
+        assert_eq!(progress.unlock(&mut session), vec![Badge::Sprint]);
+        assert_eq!(progress.unlock(&mut session), vec![]);
+    }
+}
@@ -0,0 +1,199 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[cfg(feature = "anki")]
+use crate::apkg;
+use crate::card::{Card, Fact, Factors, Rating, Status};
+use crate::deck::{self, TABLES};
+#[cfg(not(feature = "anki"))]
+use crate::error::ErrorKind;
+use crate::error::Result;
+use crate::forecast::{session_length, sessions_per_day};
+use crate::session::Session;
+
+/// Anki considers a card mature from this interval, in days
+pub(crate) const MATURE_DAYS: u32 = 21;
+
+pub struct AnkiOpts {
+    pub profile: String,
+    /// An imported deck instead of the times tables
+    pub deck: Option<String>,
+    /// Standard output when none. An `.apkg` file is written as an Anki
+    /// package instead, which keeps the schedule of the cards
+    pub file: Option<PathBuf>,
+}
+
+/// The closest Anki scheduling state of a card
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Schedule {
+    New,
+    /// Failed last time, to relearn
+    Learning,
+    Review {
+        days: u32,
+    },
+}
+
+impl Schedule {
+    /// Multa counts intervals in reviews, Anki in days
+    pub(crate) fn new(card: &Card, reviews_per_day: u32) -> Schedule {
+        let days = card.interval.div_ceil(reviews_per_day);
+        match card.status {
+            Status::Unseen => Schedule::New,
+            _ if card.last_result == Some(Rating::Bad) => Schedule::Learning,
+            Status::Learning(_) => Schedule::Review { days: days.max(1) },
+            Status::Learned(_) => Schedule::Review {
+                days: days.max(MATURE_DAYS),
+            },
+        }
+    }
+
+    fn tag(&self) -> String {
+        match self {
+            Schedule::New => "multa::new".to_string(),
+            Schedule::Learning => "multa::learning".to_string(),
+            Schedule::Review { days } => format!("multa::interval::{}", days),
+        }
+    }
+}
+
+/// Anki tags cannot contain spaces
+fn tag(name: &str) -> String {
+    name.split_whitespace().collect::<Vec<_>>().join("_")
+}
+
+/// The tags of a card: its table or deck, and its schedule
+pub(crate) fn tags(card: &Card, reviews_per_day: u32) -> Vec<String> {
+    let mut tags = vec!["multa".to_string()];
+    match &card.value {
+        Fact::Product(Factors(x, y)) => {
+            tags.push(format!("multa::table::{}", x));
+            if x != y {
+                tags.push(format!("multa::table::{}", y));
+            }
+        }
+        Fact::Custom(custom) => tags.push(format!("multa::deck::{}", tag(&custom.deck))),
+    }
+    tags.push(Schedule::new(card, reviews_per_day).tag());
+    tags
+}
+
+/// One note: front, back and tags
+fn note(card: &Card, reviews_per_day: u32) -> String {
+    // tabs would break the columns
+    let field = |text: String| text.replace('\t', " ");
+    format!(
+        "{}\t{}\t{}",
+        field(card.value.to_string()),
+        field(card.value.answer()),
+        tags(card, reviews_per_day).join(" ")
+    )
+}
+
+/// To turn intervals and due dates counted in reviews into days
+pub(crate) fn reviews_per_day(session: &Session) -> u32 {
+    session_length(&session.history) * sessions_per_day(&session.history) as u32
+}
+
+/// A notes file for File > Import in Anki. The text import cannot schedule
+/// the cards, so the interval is given as a tag: search for
+/// `tag:multa::interval::21` and use Set Due Date with `21!` to keep it.
+fn notes(session: &Session, deck: &str) -> String {
+    let reviews_per_day = reviews_per_day(session);
+    let mut cards: Vec<&Card> = session.cards.iter().collect();
+    cards.sort_by(|a, b| a.value.cmp(&b.value));
+
+    let mut out = format!(
+        "#separator:tab\n#html:false\n#notetype:Basic\n#deck:multa::{}\n#tags column:3\n",
+        deck
+    );
+    for card in cards {
+        out.push_str(&note(card, reviews_per_day));
+        out.push('\n');
+    }
+    out
+}
+
+pub fn anki(opts: AnkiOpts) -> Result<()> {
+    let mut session = Session::load(&opts.profile);
+    if let Some(name) = &opts.deck {
+        session.deck(&deck::load(name)?);
+    }
+
+    let deck = opts.deck.as_deref().unwrap_or(TABLES);
+    match opts.file {
+        Some(file) if file.extension() == Some("apkg".as_ref()) => package(&session, deck, &file)?,
+        Some(file) => fs::write(file, notes(&session, deck))?,
+        None => print!("{}", notes(&session, deck)),
+    }
+    Ok(())
+}
+
+#[cfg(feature = "anki")]
+fn package(session: &Session, deck: &str, file: &Path) -> Result<()> {
+    apkg::write(session, deck, fs::File::create(file)?)
+}
+
+#[cfg(not(feature = "anki"))]
+fn package(_: &Session, _: &str, _: &Path) -> Result<()> {
+    Err(ErrorKind::InvalidInput(
+        "Anki packages need multa built with the anki feature, \
+         write a notes file instead"
+            .to_string(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::card::Custom;
+
+    fn a_card(status: Status, interval: u32, last_result: Option<Rating>) -> Card {
+        Card {
+            status,
+            interval,
+            last_result,
+            ..Card::new(7, 8)
+        }
+    }
+
+    #[test]
+    fn anki_schedule() {
+        let good = Some(Rating::Good);
+        let schedule = |card: &Card| Schedule::new(card, 10);
+        assert_eq!(schedule(&Card::new(7, 8)), Schedule::New);
+        assert_eq!(
+            schedule(&a_card(Status::Learning(3), 2, Some(Rating::Bad))),
+            Schedule::Learning
+        );
+        assert_eq!(
+            schedule(&a_card(Status::Learning(20), 13, good)),
+            Schedule::Review { days: 2 }
+        );
+        assert_eq!(
+            schedule(&a_card(Status::Learning(20), 3, good)),
+            Schedule::Review { days: 1 }
+        );
+        assert_eq!(
+            schedule(&a_card(Status::Learned(60), 55, good)),
+            Schedule::Review { days: MATURE_DAYS }
+        );
+    }
+
+    #[test]
+    fn anki_note() {
+        assert_eq!(
+            note(&Card::new(7, 8), 10),
+            "7 x 8\t56\tmulta multa::table::7 multa::table::8 multa::new"
+        );
+        let square = Card::with_fact(Fact::Custom(Custom {
+            deck: "my squares".to_string(),
+            prompt: "9²".to_string(),
+            answer: "81".to_string(),
+        }));
+        assert_eq!(
+            note(&square, 10),
+            "9²\t81\tmulta multa::deck::my_squares multa::new"
+        );
+    }
+}
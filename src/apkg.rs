@@ -0,0 +1,336 @@
+use rusqlite::{params, Connection, DatabaseName};
+use serde_json::json;
+use std::io::{Seek, Write};
+use zip::write::FileOptions;
+use zip::ZipWriter;
+
+use crate::anki::{self, Schedule};
+use crate::card::{Card, Status};
+use crate::error::Result;
+use crate::session::{now, Session};
+
+/// The Basic note type written in the package, the same each export so that
+/// Anki updates the notes imported before
+const MODEL_ID: i64 = 1_425_279_151_691;
+
+/// Anki's default ease, in permille
+const FACTOR: u32 = 2500;
+
+const SCHEMA: &str = "
+CREATE TABLE col (
+    id integer primary key, crt integer not null, mod integer not null,
+    scm integer not null, ver integer not null, dty integer not null,
+    usn integer not null, ls integer not null, conf text not null,
+    models text not null, decks text not null, dconf text not null,
+    tags text not null
+);
+CREATE TABLE notes (
+    id integer primary key, guid text not null, mid integer not null,
+    mod integer not null, usn integer not null, tags text not null,
+    flds text not null, sfld integer not null, csum integer not null,
+    flags integer not null, data text not null
+);
+CREATE TABLE cards (
+    id integer primary key, nid integer not null, did integer not null,
+    ord integer not null, mod integer not null, usn integer not null,
+    type integer not null, queue integer not null, due integer not null,
+    ivl integer not null, factor integer not null, reps integer not null,
+    lapses integer not null, left integer not null, odue integer not null,
+    odid integer not null, flags integer not null, data text not null
+);
+CREATE TABLE revlog (
+    id integer primary key, cid integer not null, usn integer not null,
+    ease integer not null, ivl integer not null, lastIvl integer not null,
+    factor integer not null, time integer not null, type integer not null
+);
+CREATE TABLE graves (
+    usn integer not null, oid integer not null, type integer not null
+);
+";
+
+/// Anki fields are HTML
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Anki finds duplicate notes from the first 8 hex digits of the SHA-1 of
+/// their first field
+fn checksum(field: &str) -> i64 {
+    let digest = sha1_smol::Sha1::from(field).digest().bytes();
+    i64::from(u32::from_be_bytes([
+        digest[0], digest[1], digest[2], digest[3],
+    ]))
+}
+
+/// The same for each deck name, so that exports land in the same Anki deck
+fn deck_id(name: &str) -> i64 {
+    let digest = sha1_smol::Sha1::from(name).digest().bytes();
+    // Positive, and clear of the default deck 1
+    i64::from(u32::from_be_bytes([
+        digest[0], digest[1], digest[2], digest[3],
+    ])) + 2
+}
+
+/// The collection table: the Basic note type and the deck of the cards
+fn collection(db: &Connection, deck: &str, did: i64, now: u64) -> Result<()> {
+    let ms = now as i64 * 1000;
+    let fields: Vec<_> = ["Front", "Back"]
+        .iter()
+        .enumerate()
+        .map(|(ord, name)| {
+            json!({
+                "name": name, "ord": ord, "sticky": false, "rtl": false,
+                "font": "Arial", "size": 20, "media": [],
+            })
+        })
+        .collect();
+    let models = json!({
+        MODEL_ID.to_string(): {
+            "id": MODEL_ID, "name": "multa", "type": 0, "mod": now, "usn": -1,
+            "sortf": 0, "did": did, "tags": [], "vers": [],
+            "req": [[0, "any", [0]]],
+            "flds": fields,
+            "tmpls": [{
+                "name": "Card 1", "ord": 0, "did": null, "bqfmt": "", "bafmt": "",
+                "qfmt": "{{Front}}",
+                "afmt": "{{FrontSide}}<hr id=answer>{{Back}}",
+            }],
+            "css": ".card { font-family: arial; font-size: 20px; text-align: center; }",
+            "latexPre": "", "latexPost": "",
+        }
+    });
+    let a_deck = |id: i64, name: &str| {
+        json!({
+            "id": id, "name": name, "mod": now, "usn": -1, "desc": "",
+            "dyn": 0, "conf": 1, "collapsed": false, "extendNew": 10, "extendRev": 50,
+            "lrnToday": [0, 0], "revToday": [0, 0], "newToday": [0, 0], "timeToday": [0, 0],
+        })
+    };
+    let decks = json!({
+        "1": a_deck(1, "Default"),
+        did.to_string(): a_deck(did, &format!("multa::{}", deck)),
+    });
+    let conf = json!({
+        "activeDecks": [1], "curDeck": 1, "newSpread": 0, "collapseTime": 1200,
+        "timeLim": 0, "estTimes": true, "dueCounts": true, "curModel": null,
+        "nextPos": 1, "sortType": "noteFld", "sortBackwards": false, "addToCur": true,
+    });
+    // Anki's default options
+    let dconf = json!({
+        "1": {
+            "id": 1, "name": "Default", "mod": 0, "usn": 0, "maxTaken": 60,
+            "autoplay": true, "timer": 0, "replayq": true, "dyn": false,
+            "new": {
+                "bury": true, "delays": [1, 10], "initialFactor": FACTOR,
+                "ints": [1, 4, 7], "order": 1, "perDay": 20, "separate": true,
+            },
+            "lapse": {
+                "delays": [10], "leechAction": 0, "leechFails": 8,
+                "minInt": 1, "mult": 0,
+            },
+            "rev": {
+                "bury": true, "ease4": 1.3, "fuzz": 0.05, "ivlFct": 1,
+                "maxIvl": 36500, "minSpace": 1, "perDay": 100,
+            },
+        }
+    });
+    db.execute(
+        "INSERT INTO col VALUES (1, ?, ?, ?, 11, 0, 0, 0, ?, ?, ?, ?, '{}')",
+        params![
+            // Day 0 of the collection is today
+            (now - now % 86_400) as i64,
+            ms,
+            ms,
+            conf.to_string(),
+            models.to_string(),
+            decks.to_string(),
+            dconf.to_string(),
+        ],
+    )?;
+    Ok(())
+}
+
+/// Days from now until the card is due, for the review cards
+fn due_days(card: &Card, tick: u32, reviews_per_day: u32) -> u32 {
+    match card.status {
+        Status::Learning(due) | Status::Learned(due) => {
+            due.saturating_sub(tick).div_ceil(reviews_per_day)
+        }
+        Status::Unseen => 0,
+    }
+}
+
+/// The `type`, `queue`, `ivl` and `due` columns of an Anki card: new cards
+/// are due by `position`, learning ones at a time in seconds and review ones
+/// on a day of the collection, today being day 0
+fn columns(schedule: &Schedule, position: u32, now: u64, due: u32) -> (i64, i64, i64, i64) {
+    match *schedule {
+        Schedule::New => (0, 0, 0, position.into()),
+        Schedule::Learning => (3, 1, 1, now as i64),
+        Schedule::Review { days } => (2, 2, days.into(), due.into()),
+    }
+}
+
+/// The cards of `session` as an Anki collection, in memory, scheduled as
+/// they are in multa
+fn collection_of(session: &Session, deck: &str) -> Result<Connection> {
+    let now = now().unwrap_or_default();
+    let reviews_per_day = anki::reviews_per_day(session);
+    let did = deck_id(deck);
+    let mut cards: Vec<&Card> = session.cards.iter().collect();
+    cards.sort_by(|a, b| a.value.cmp(&b.value));
+
+    let mut db = Connection::open_in_memory()?;
+    db.execute_batch(SCHEMA)?;
+    collection(&db, deck, did, now)?;
+
+    let tx = db.transaction()?;
+    for (position, card) in cards.into_iter().enumerate() {
+        // Note and card ids are creation times in milliseconds
+        let id = now as i64 * 1000 + position as i64;
+        let front = card.value.to_string();
+        let reps = session
+            .history
+            .iter()
+            .filter(|review| review.value == card.value)
+            .count();
+        tx.execute(
+            "INSERT INTO notes VALUES (?, ?, ?, ?, -1, ?, ?, ?, ?, 0, '')",
+            params![
+                id,
+                format!("multa::{}", front),
+                MODEL_ID,
+                now as i64,
+                format!(" {} ", anki::tags(card, reviews_per_day).join(" ")),
+                format!("{}\x1f{}", escape(&front), escape(&card.value.answer())),
+                front,
+                checksum(&front),
+            ],
+        )?;
+        let schedule = Schedule::new(card, reviews_per_day);
+        let due = due_days(card, session.tick, reviews_per_day);
+        let (kind, queue, ivl, due) = columns(&schedule, position as u32, now, due);
+        tx.execute(
+            "INSERT INTO cards VALUES (?, ?, ?, 0, ?, -1, ?, ?, ?, ?, ?, ?, ?, ?, 0, 0, 0, '')",
+            params![
+                id,
+                id,
+                did,
+                now as i64,
+                kind,
+                queue,
+                due,
+                ivl,
+                if schedule == Schedule::New { 0 } else { FACTOR },
+                reps as i64,
+                card.lapses,
+                // One relearning step left, to do today
+                if schedule == Schedule::Learning {
+                    1001
+                } else {
+                    0
+                },
+            ],
+        )?;
+    }
+    tx.commit()?;
+    Ok(db)
+}
+
+/// An Anki package of the cards of `session`: the collection and an empty
+/// list of media, zipped
+pub(crate) fn write<W: Write + Seek>(session: &Session, deck: &str, out: W) -> Result<()> {
+    let db = collection_of(session, deck)?;
+    let mut zip = ZipWriter::new(out);
+    zip.start_file("collection.anki2", FileOptions::default())?;
+    zip.write_all(&db.serialize(DatabaseName::Main)?)?;
+    zip.start_file("media", FileOptions::default())?;
+    zip.write_all(b"{}")?;
+    zip.finish()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::anki::MATURE_DAYS;
+    use crate::card::Rating;
+    use std::io::{Cursor, Read};
+
+    fn a_card(status: Status, interval: u32, last_result: Option<Rating>) -> Card {
+        Card {
+            status,
+            interval,
+            last_result,
+            ..Card::new(7, 8)
+        }
+    }
+
+    #[test]
+    fn apkg_columns() {
+        let good = Some(Rating::Good);
+        let columns_of = |card: &Card| {
+            let schedule = Schedule::new(card, 10);
+            columns(&schedule, 4, 1000, due_days(card, 100, 10))
+        };
+        assert_eq!(columns_of(&Card::new(7, 8)), (0, 0, 0, 4));
+        assert_eq!(
+            columns_of(&a_card(Status::Learning(103), 2, Some(Rating::Bad))),
+            (3, 1, 1, 1000)
+        );
+        assert_eq!(
+            columns_of(&a_card(Status::Learning(113), 13, good)),
+            (2, 2, 2, 2)
+        );
+        assert_eq!(
+            columns_of(&a_card(Status::Learning(90), 3, good)),
+            (2, 2, 1, 0)
+        );
+        assert_eq!(
+            columns_of(&a_card(Status::Learned(155), 55, good)),
+            (2, 2, MATURE_DAYS.into(), 6)
+        );
+    }
+
+    #[test]
+    fn apkg_checksum() {
+        assert_eq!(checksum("7 x 8"), 0x3f74_deeb);
+        assert_eq!(escape("a < b & c"), "a &lt; b &amp; c");
+    }
+
+    #[test]
+    fn apkg_write() {
+        let mut session = Session::from(vec![
+            a_card(Status::Learned(30), 55, Some(Rating::Good)),
+            Card::new(6, 9),
+        ]);
+        session.tick = 10;
+
+        let db = collection_of(&session, "tables").unwrap();
+        let mut rows = db
+            .prepare("SELECT type, queue, ivl, due FROM cards ORDER BY due DESC")
+            .unwrap();
+        let cards: Vec<(i64, i64, i64, i64)> = rows
+            .query_map([], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+            })
+            .unwrap()
+            .map(|card| card.unwrap())
+            .collect();
+        // 20 reviews a day by default: due in 20 reviews is tomorrow
+        assert_eq!(cards, [(2, 2, MATURE_DAYS.into(), 1), (0, 0, 0, 0)]);
+
+        let mut out = Cursor::new(Vec::new());
+        write(&session, "tables", &mut out).unwrap();
+        let mut zip = zip::ZipArchive::new(out).unwrap();
+        let mut media = String::new();
+        zip.by_name("media")
+            .unwrap()
+            .read_to_string(&mut media)
+            .unwrap();
+        assert_eq!(media, "{}");
+        assert!(zip.by_name("collection.anki2").unwrap().size() > 0);
+    }
+}
@@ -0,0 +1,336 @@
+use chrono::{Local, NaiveDate, TimeZone};
+use crossterm::{cursor, execute, style};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::stdout;
+use std::path::PathBuf;
+
+use crate::card::Card;
+use crate::class::Roster;
+use crate::engine::State;
+use crate::error::{ErrorKind, Result};
+use crate::history::Completion;
+use crate::lock::{Conflict, Profile};
+use crate::session::{self, Session, TABLES};
+use crate::tui;
+
+/// A practice set given by the teacher
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Assignment {
+    pub name: String,
+    /// Only the facts of these tables are asked
+    pub tables: Vec<u8>,
+    /// Number of cards to answer
+    pub cards: usize,
+    pub due: NaiveDate,
+}
+
+pub enum AssignmentCommand {
+    /// Does the assignment named, or the first one left to do
+    Do {
+        profile: String,
+        name: Option<String>,
+        conflict: Conflict,
+    },
+    Create(Assignment),
+    List,
+    Report {
+        name: String,
+        class: String,
+    },
+}
+
+impl Assignment {
+    pub fn contains(&self, card: &Card) -> bool {
+        self.tables.iter().any(|&table| card.value.in_table(table))
+    }
+
+    /// An error unless the assignment can be completed
+    fn validate(&self) -> Result<()> {
+        if let Some(table) = self.tables.iter().find(|&&table| !TABLES.contains(&table)) {
+            return Err(ErrorKind::InvalidInput(format!(
+                "no table {}, the tables go from {} to {}",
+                table,
+                TABLES.start,
+                TABLES.end - 1
+            )));
+        }
+        if self.cards == 0 {
+            return Err(ErrorKind::InvalidInput(
+                "an assignment needs at least one card".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// The assignments of every class, shared by all the profiles
+fn assignments_path() -> PathBuf {
+    session::data_dir().join("assignments.json")
+}
+
+fn load() -> Result<Vec<Assignment>> {
+    match fs::read_to_string(assignments_path()) {
+        Ok(json) => Ok(serde_json::from_str(&json)?),
+        Err(_) => Ok(Vec::new()),
+    }
+}
+
+fn save(assignments: &[Assignment]) -> Result<()> {
+    let path = assignments_path();
+    fs::create_dir_all(path.parent().unwrap())?;
+    fs::write(path, serde_json::to_string(assignments)?)?;
+    Ok(())
+}
+
+fn completion<'a>(session: &'a Session, name: &str) -> Option<&'a Completion> {
+    session
+        .assignments
+        .iter()
+        .find(|completion| completion.assignment == name)
+}
+
+/// The assignment named `name`, or else the first one due not done yet
+fn pick<'a>(
+    assignments: &'a [Assignment],
+    session: &Session,
+    name: Option<&str>,
+) -> Option<&'a Assignment> {
+    match name {
+        Some(name) => assignments
+            .iter()
+            .find(|assignment| assignment.name == name),
+        None => assignments
+            .iter()
+            .filter(|assignment| completion(session, &assignment.name).is_none())
+            .min_by_key(|assignment| assignment.due),
+    }
+}
+
+fn date(time: u64) -> NaiveDate {
+    Local
+        .timestamp_opt(time as i64, 0)
+        .single()
+        .map(|time| time.date_naive())
+        .unwrap_or_default()
+}
+
+/// One line per student of `class`: when they finished and their score
+fn report(assignment: &Assignment, students: &[(String, Session)]) -> Vec<String> {
+    students
+        .iter()
+        .map(
+            |(profile, session)| match completion(session, &assignment.name) {
+                Some(done) => {
+                    let finished = date(done.time);
+                    format!(
+                        "{}: done on {}{}, {}/{}",
+                        profile,
+                        finished,
+                        if finished > assignment.due {
+                            " (late)"
+                        } else {
+                            ""
+                        },
+                        done.good,
+                        done.good + done.bad
+                    )
+                }
+                None => format!("{}: not done", profile),
+            },
+        )
+        .collect()
+}
+
+fn practice(profile: String, name: Option<String>, conflict: Conflict) -> Result<()> {
+    let assignments = load()?;
+    let profile = Profile::open(&profile, conflict)?;
+    let mut session = profile.load();
+    let assignment = match pick(&assignments, &session, name.as_deref()) {
+        Some(assignment) => assignment,
+        None if name.is_some() => {
+            return Err(ErrorKind::InvalidInput(format!(
+                "no assignment named {}",
+                name.unwrap_or_default()
+            )))
+        }
+        None => {
+            println!("No assignment to do");
+            return Ok(());
+        }
+    };
+
+    session.focus(|card| assignment.contains(card));
+    let mut state = State::new(&session, false, false);
+    state.limit(assignment.cards);
+    tui::play(&mut session, &mut state, None)?;
+
+    let summary = state.summary();
+    execute!(stdout(), style::Print(summary), cursor::MoveToNextLine(1))?;
+    if summary.ok + summary.ko == assignment.cards {
+        session.assignments.push(Completion {
+            assignment: assignment.name.clone(),
+            time: session::now().unwrap_or_default(),
+            good: summary.ok,
+            bad: summary.ko,
+        });
+        println!("Assignment {} done!", assignment.name);
+    }
+    profile.save(&session)
+}
+
+pub fn assignment(command: AssignmentCommand) -> Result<()> {
+    match command {
+        AssignmentCommand::Do {
+            profile,
+            name,
+            conflict,
+        } => practice(profile, name, conflict),
+        AssignmentCommand::Create(assignment) => {
+            assignment.validate()?;
+            let mut assignments = load()?;
+            if assignments.iter().any(|a| a.name == assignment.name) {
+                return Err(ErrorKind::InvalidInput(format!(
+                    "assignment {} already exists",
+                    assignment.name
+                )));
+            }
+            assignments.push(assignment);
+            save(&assignments)
+        }
+        AssignmentCommand::List => {
+            for assignment in load()? {
+                let tables: Vec<String> = assignment.tables.iter().map(u8::to_string).collect();
+                println!(
+                    "{}: {} cards of the {} tables, due {}",
+                    assignment.name,
+                    assignment.cards,
+                    tables.join(", "),
+                    assignment.due
+                );
+            }
+            Ok(())
+        }
+        AssignmentCommand::Report { name, class } => {
+            let assignment = load()?
+                .into_iter()
+                .find(|assignment| assignment.name == name)
+                .ok_or_else(|| ErrorKind::InvalidInput(format!("no assignment named {}", name)))?;
+            let students: Vec<(String, Session)> = Roster::load(&class)?
+                .students
+                .into_iter()
+                .map(|student| {
+                    let session = Session::load(&student);
+                    (student, session)
+                })
+                .collect();
+
+            for line in report(&assignment, &students) {
+                println!("{}", line);
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Datelike;
+
+    fn an_assignment(name: &str, due: u32) -> Assignment {
+        Assignment {
+            name: name.to_string(),
+            tables: vec![7],
+            cards: 10,
+            due: NaiveDate::from_ymd_opt(2024, 1, due).unwrap(),
+        }
+    }
+
+    fn a_completion(name: &str, time: u64) -> Completion {
+        Completion {
+            assignment: name.to_string(),
+            time,
+            good: 8,
+            bad: 2,
+        }
+    }
+
+    #[test]
+    fn assignment_rejects_unknown_tables() {
+        assert!(an_assignment("a", 10).validate().is_ok());
+        for table in [1, 10, 12] {
+            let assignment = Assignment {
+                tables: vec![7, table],
+                ..an_assignment("a", 10)
+            };
+            assert!(matches!(
+                assignment.validate(),
+                Err(ErrorKind::InvalidInput(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn assignment_rejects_no_cards() {
+        let assignment = Assignment {
+            cards: 0,
+            ..an_assignment("a", 10)
+        };
+        assert!(matches!(
+            assignment.validate(),
+            Err(ErrorKind::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn assignment_pick() {
+        let assignments = [an_assignment("b", 20), an_assignment("a", 10)];
+        let mut session = Session::from(vec![]);
+
+        assert_eq!(pick(&assignments, &session, None).unwrap().name, "a");
+        session.assignments.push(a_completion("a", 0));
+        assert_eq!(pick(&assignments, &session, None).unwrap().name, "b");
+        assert_eq!(pick(&assignments, &session, Some("a")).unwrap().name, "a");
+        assert!(pick(&assignments, &session, Some("c")).is_none());
+
+        assert!(assignments[0].contains(&Card::new(7, 2)));
+        assert!(!assignments[0].contains(&Card::new(6, 2)));
+    }
+
+    #[test]
+    fn assignment_report() {
+        let assignment = an_assignment("a", 10);
+        let noon = |day: u32| {
+            Local
+                .from_local_datetime(
+                    &assignment
+                        .due
+                        .with_day(day)
+                        .unwrap()
+                        .and_hms_opt(12, 0, 0)
+                        .unwrap(),
+                )
+                .unwrap()
+                .timestamp() as u64
+        };
+        let mut alice = Session::from(vec![]);
+        alice.assignments.push(a_completion("a", noon(9)));
+        let mut bob = Session::from(vec![]);
+        bob.assignments.push(a_completion("a", noon(11)));
+        let students = vec![
+            ("alice".to_string(), alice),
+            ("bob".to_string(), bob),
+            ("carol".to_string(), Session::from(vec![])),
+        ];
+
+        assert_eq!(
+            report(&assignment, &students),
+            [
+                "alice: done on 2024-01-09, 8/10",
+                "bob: done on 2024-01-11 (late), 8/10",
+                "carol: not done",
+            ]
+        );
+    }
+}
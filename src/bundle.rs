@@ -0,0 +1,112 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{self, Read};
+use std::path::PathBuf;
+
+use crate::error::{ErrorKind, Result};
+use crate::lock::{Conflict, Profile};
+use crate::session::{self, Merge, Session};
+
+/// Bumped whenever a bundle stops being readable by older versions
+const VERSION: u32 = 1;
+
+/// A whole profile in one file, to move it to another machine. A profile has
+/// no settings of its own to carry: the options of a practice are given on
+/// the command line, and the sync directory is a path of this machine.
+#[derive(Debug, Serialize, Deserialize)]
+struct Bundle {
+    version: u32,
+    /// The name of the profile it was exported from
+    profile: String,
+    exported: u64,
+    /// The profile as it is stored on disk
+    session: serde_json::Value,
+}
+
+pub struct ExportOpts {
+    pub profile: String,
+    /// Standard output when none
+    pub file: Option<PathBuf>,
+}
+
+pub struct ImportOpts {
+    pub profile: String,
+    /// Standard input when none
+    pub file: Option<PathBuf>,
+    pub strategy: Merge,
+    pub conflict: Conflict,
+}
+
+fn bundle(profile: &str, session: &Session) -> Result<String> {
+    let bundle = Bundle {
+        version: VERSION,
+        profile: profile.to_string(),
+        exported: session::now().unwrap_or_default(),
+        session: serde_json::from_str(&session.to_json()?)?,
+    };
+    Ok(serde_json::to_string(&bundle)?)
+}
+
+fn unbundle(json: &str) -> Result<Session> {
+    let bundle: Bundle = serde_json::from_str(json)?;
+    if bundle.version > VERSION {
+        return Err(ErrorKind::InvalidInput(format!(
+            "the bundle was exported by a newer version of multa (format {})",
+            bundle.version
+        )));
+    }
+    Session::from_json(&bundle.session.to_string())
+}
+
+pub fn export(opts: ExportOpts) -> Result<()> {
+    let json = bundle(&opts.profile, &Session::load(&opts.profile))?;
+    match opts.file {
+        Some(file) => fs::write(file, json)?,
+        None => println!("{}", json),
+    }
+    Ok(())
+}
+
+/// Merges the bundle into the profile, which is created if it does not
+/// exist yet
+pub fn import(opts: ImportOpts) -> Result<()> {
+    let json = match opts.file {
+        Some(file) => fs::read_to_string(file)?,
+        None => {
+            let mut json = String::new();
+            io::stdin().read_to_string(&mut json)?;
+            json
+        }
+    };
+    let imported = unbundle(&json)?;
+
+    let profile = Profile::open(&opts.profile, opts.conflict)?;
+    let mut session = profile.load();
+    let before = session.history.len();
+    session.merge(imported, opts.strategy);
+    println!(
+        "Imported {} new reviews into {}",
+        session.history.len() - before,
+        opts.profile
+    );
+    profile.save(&session)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::card::{Card, Rating};
+
+    #[test]
+    fn bundle_roundtrip() {
+        let mut session = Session::from(vec![Card::new(7, 8)]);
+        session.review(Rating::Bad);
+
+        let imported = unbundle(&bundle("alice", &session).unwrap()).unwrap();
+        assert_eq!(imported.history, session.history);
+        assert_eq!(imported.to_json().unwrap(), session.to_json().unwrap());
+
+        let future = r#"{"version":99,"profile":"alice","exported":0,"session":{}}"#;
+        assert!(matches!(unbundle(future), Err(ErrorKind::InvalidInput(_))));
+    }
+}
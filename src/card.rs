@@ -3,7 +3,10 @@ use rand::Rng;
 use serde::{Deserialize, Serialize};
 use std::fmt;
 
-#[derive(Hash, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+/// Number of lapses after which a card is considered a leech
+pub const LEECH_THRESHOLD: u32 = 4;
+
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Serialize, Deserialize)]
 pub struct Factors(pub u8, pub u8);
 
 impl Factors {
@@ -11,6 +14,66 @@ impl Factors {
         let Factors(x, y) = self;
         x * y
     }
+
+    /// Facts of the table whose product is `n`
+    pub fn with_product(n: u8) -> Vec<Factors> {
+        (2..10)
+            .flat_map(|x| (2..10).map(move |y| Factors(x, y)))
+            .filter(|factors| factors.compute() == n)
+            .collect()
+    }
+
+    /// The fact just before this one in the table, e.g. 7 x 7 for 7 x 8
+    pub fn neighbour(&self) -> Factors {
+        let Factors(x, y) = *self;
+        Factors(x, y - 1)
+    }
+}
+
+/// A fact of an imported deck, e.g. a square or a number bond
+#[derive(Hash, Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Serialize, Deserialize)]
+pub struct Custom {
+    pub deck: String,
+    pub prompt: String,
+    pub answer: String,
+}
+
+/// What a card asks. Stored as the bare factors for the times tables, as
+/// before custom decks.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Fact {
+    Product(Factors),
+    Custom(Custom),
+}
+
+impl Fact {
+    pub fn answer(&self) -> String {
+        match self {
+            Fact::Product(factors) => factors.compute().to_string(),
+            Fact::Custom(custom) => custom.answer.clone(),
+        }
+    }
+
+    /// None for the facts of custom decks
+    pub fn factors(&self) -> Option<Factors> {
+        match self {
+            Fact::Product(factors) => Some(*factors),
+            Fact::Custom(_) => None,
+        }
+    }
+
+    /// Whether this is a fact of the times table of `table`
+    pub fn in_table(&self, table: u8) -> bool {
+        self.factors()
+            .is_some_and(|Factors(x, y)| x == table || y == table)
+    }
+}
+
+impl From<Factors> for Fact {
+    fn from(factors: Factors) -> Fact {
+        Fact::Product(factors)
+    }
 }
 
 #[derive(Hash, Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
@@ -38,24 +101,37 @@ pub enum Rating {
 
 #[derive(Hash, Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
 pub struct Card {
-    pub value: Factors,
+    pub value: Fact,
     pub interval: u32,
     pub status: Status,
     pub last_result: Option<Rating>,
     pub last_seen: Option<u64>,
+    /// Number of wrong answers once the card has been seen, since it was
+    /// last learned
+    #[serde(default)]
+    pub lapses: u32,
 }
 
 impl Card {
     pub fn new(x: u8, y: u8) -> Card {
+        Card::with_fact(Factors(x, y).into())
+    }
+
+    pub fn with_fact(value: Fact) -> Card {
         Card {
-            value: Factors(x, y),
+            value,
             interval: 55,
             status: Status::Unseen,
             // TODO: move last_result and last_seen to status?
             last_result: None,
             last_seen: None,
+            lapses: 0,
         }
     }
+
+    pub fn is_leech(&self) -> bool {
+        self.lapses >= LEECH_THRESHOLD
+    }
 }
 
 impl Distribution<Factors> for Standard {
@@ -77,3 +153,18 @@ impl fmt::Debug for Factors {
         fmt::Display::fmt(self, f)
     }
 }
+
+impl fmt::Display for Fact {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Fact::Product(factors) => fmt::Display::fmt(factors, f),
+            Fact::Custom(custom) => write!(f, "{}", custom.prompt),
+        }
+    }
+}
+
+impl fmt::Debug for Fact {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
@@ -0,0 +1,324 @@
+use chrono::{Duration, Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::PathBuf;
+
+use crate::card::Factors;
+use crate::error::{ErrorKind, Result};
+use crate::history::{self, Review, Stats};
+use crate::session::{self, Session, TABLES};
+
+const TOP_HARDEST: usize = 5;
+/// Accuracy is computed over the last days only
+const RECENT_DAYS: i64 = 7;
+
+pub enum ClassCommand {
+    Create {
+        class: String,
+        students: Vec<String>,
+    },
+    Add {
+        class: String,
+        students: Vec<String>,
+    },
+    Remove {
+        class: String,
+        students: Vec<String>,
+    },
+    List,
+    Report {
+        class: String,
+        json: bool,
+    },
+}
+
+/// The profiles of a class, stored next to them
+#[derive(Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Roster {
+    pub students: Vec<String>,
+}
+
+fn classes_dir() -> PathBuf {
+    session::data_dir().join("classes")
+}
+
+fn roster_path(class: &str) -> PathBuf {
+    classes_dir().join(format!("{}.json", class))
+}
+
+impl Roster {
+    pub fn load(class: &str) -> Result<Roster> {
+        match fs::read_to_string(roster_path(class)) {
+            Ok(json) => Ok(serde_json::from_str(&json)?),
+            Err(_) => Err(ErrorKind::InvalidInput(format!("no class named {}", class))),
+        }
+    }
+
+    pub fn save(&self, class: &str) -> Result<()> {
+        fs::create_dir_all(classes_dir())?;
+        fs::write(roster_path(class), serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+    pub fn add(&mut self, students: Vec<String>) {
+        for student in students {
+            if !self.students.contains(&student) {
+                self.students.push(student);
+            }
+        }
+    }
+
+    pub fn remove(&mut self, students: &[String]) {
+        self.students.retain(|student| !students.contains(student));
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct StudentReport {
+    pub profile: String,
+    /// Average mastery of the tables, from 0 to 1
+    pub mastery: f64,
+    /// Over the last `RECENT_DAYS` days
+    pub accuracy: Option<f64>,
+    pub streak: usize,
+    pub reviews: usize,
+    /// The least mastered table, once the student has practiced
+    pub weakest_table: Option<u8>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct HardFact {
+    pub x: u8,
+    pub y: u8,
+    pub good: usize,
+    pub bad: usize,
+    pub accuracy: Option<f64>,
+}
+
+/// Everything shown by `multa class report`, also its JSON output
+#[derive(Debug, PartialEq, Serialize)]
+pub struct ClassReport {
+    pub students: Vec<StudentReport>,
+    /// The facts of the tables with the most wrong answers across the class
+    pub hardest: Vec<HardFact>,
+}
+
+impl StudentReport {
+    fn new(profile: &str, session: &Session, today: NaiveDate) -> StudentReport {
+        let since = today - Duration::days(RECENT_DAYS - 1);
+        let mut recent = Stats::default();
+        session
+            .history
+            .iter()
+            .filter(|review| review.date() >= since)
+            .for_each(|review| recent.add(review.rating));
+
+        let dates: BTreeSet<NaiveDate> = session.history.iter().map(Review::date).collect();
+        let masteries: Vec<(u8, f64)> = TABLES
+            .map(|table| (table, session.mastery(table)))
+            .collect();
+
+        StudentReport {
+            profile: profile.to_string(),
+            mastery: masteries.iter().map(|(_, m)| m).sum::<f64>() / masteries.len() as f64,
+            accuracy: recent.accuracy(),
+            streak: history::streak(&dates, today),
+            reviews: session.history.len(),
+            weakest_table: masteries
+                .iter()
+                .min_by(|(_, a), (_, b)| a.total_cmp(b))
+                .map(|&(table, _)| table)
+                .filter(|_| !session.history.is_empty()),
+        }
+    }
+}
+
+impl ClassReport {
+    pub fn new(sessions: &[(String, Session)], today: NaiveDate) -> ClassReport {
+        let mut stats: HashMap<Factors, Stats> = HashMap::new();
+        for (_, session) in sessions {
+            let facts = history::stats_by_fact(&session.history).into_iter();
+            for (value, fact) in facts.filter_map(|(value, fact)| Some((value.factors()?, fact))) {
+                let total = stats.entry(value).or_default();
+                total.good += fact.good;
+                total.bad += fact.bad;
+            }
+        }
+
+        let mut hardest: Vec<HardFact> = stats
+            .into_iter()
+            .filter(|(_, stats)| stats.bad > 0)
+            .map(|(value, stats)| HardFact {
+                x: value.0,
+                y: value.1,
+                good: stats.good,
+                bad: stats.bad,
+                accuracy: stats.accuracy(),
+            })
+            .collect();
+        hardest.sort_by_key(|fact| (Reverse(fact.bad), fact.good, fact.x, fact.y));
+        hardest.truncate(TOP_HARDEST);
+
+        ClassReport {
+            students: sessions
+                .iter()
+                .map(|(profile, session)| StudentReport::new(profile, session, today))
+                .collect(),
+            hardest,
+        }
+    }
+}
+
+fn percent(value: Option<f64>) -> String {
+    value
+        .map(|value| format!("{:.0}%", value * 100.0))
+        .unwrap_or_else(|| "-".to_string())
+}
+
+fn print_report(report: &ClassReport) {
+    let width = report
+        .students
+        .iter()
+        .map(|student| student.profile.len())
+        .max()
+        .unwrap_or(0)
+        .max("Student".len());
+
+    println!(
+        "{:width$}  Mastery  Accuracy  Streak  Reviews  Weakest",
+        "Student",
+        width = width
+    );
+    for student in &report.students {
+        println!(
+            "{:width$}  {:>7}  {:>8}  {:>6}  {:>7}  {}",
+            student.profile,
+            percent(Some(student.mastery)),
+            percent(student.accuracy),
+            student.streak,
+            student.reviews,
+            student
+                .weakest_table
+                .map(|table| format!("{}s", table))
+                .unwrap_or_else(|| "-".to_string()),
+            width = width
+        );
+    }
+
+    if !report.hardest.is_empty() {
+        println!();
+        println!("Hardest facts");
+        for fact in &report.hardest {
+            println!(
+                "{} = {}  {} KO / {} ({})",
+                Factors(fact.x, fact.y),
+                fact.x * fact.y,
+                fact.bad,
+                fact.good + fact.bad,
+                percent(fact.accuracy)
+            );
+        }
+    }
+}
+
+pub fn class(command: ClassCommand) -> Result<()> {
+    match command {
+        ClassCommand::Create { class, students } => {
+            if roster_path(&class).exists() {
+                return Err(ErrorKind::InvalidInput(format!(
+                    "class {} already exists",
+                    class
+                )));
+            }
+            let mut roster = Roster::default();
+            roster.add(students);
+            roster.save(&class)
+        }
+        ClassCommand::Add { class, students } => {
+            let mut roster = Roster::load(&class)?;
+            roster.add(students);
+            roster.save(&class)
+        }
+        ClassCommand::Remove { class, students } => {
+            let mut roster = Roster::load(&class)?;
+            roster.remove(&students);
+            roster.save(&class)
+        }
+        ClassCommand::List => {
+            for class in session::json_names(&classes_dir()) {
+                let roster = Roster::load(&class)?;
+                println!("{}: {}", class, roster.students.join(", "));
+            }
+            Ok(())
+        }
+        ClassCommand::Report { class, json } => {
+            let roster = Roster::load(&class)?;
+            let sessions: Vec<(String, Session)> = roster
+                .students
+                .into_iter()
+                .map(|student| {
+                    let session = Session::load(&student);
+                    (student, session)
+                })
+                .collect();
+            let report = ClassReport::new(&sessions, Local::now().date_naive());
+
+            if json {
+                println!("{}", serde_json::to_string(&report)?);
+            } else {
+                print_report(&report);
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::card::{Card, Rating};
+
+    #[test]
+    fn class_roster() {
+        let mut roster = Roster::default();
+        roster.add(vec!["alice".to_string(), "bob".to_string()]);
+        roster.add(vec!["bob".to_string(), "carol".to_string()]);
+        roster.remove(&["alice".to_string()]);
+        assert_eq!(roster.students, ["bob", "carol"]);
+    }
+
+    #[test]
+    fn class_report() {
+        let today = Local::now().date_naive();
+        let mut alice = Session::from(vec![Card::new(7, 8), Card::new(6, 9)]);
+        alice.review(Rating::Bad);
+        alice.review(Rating::Good);
+        let mut bob = Session::from(vec![Card::new(7, 8)]);
+        bob.review(Rating::Bad);
+        let sessions = vec![
+            ("alice".to_string(), alice),
+            ("bob".to_string(), bob),
+            ("carol".to_string(), Session::from(vec![])),
+        ];
+
+        let report = ClassReport::new(&sessions, today);
+        assert_eq!(report.students.len(), 3);
+        assert_eq!(report.students[0].accuracy, Some(0.5));
+        assert_eq!(report.students[0].reviews, 2);
+        assert_eq!(report.students[1].streak, 1);
+        assert_eq!(report.students[2].weakest_table, None);
+        assert_eq!(report.hardest.len(), 1);
+        assert_eq!(
+            report.hardest[0],
+            HardFact {
+                x: 7,
+                y: 8,
+                good: 0,
+                bad: 2,
+                accuracy: Some(0.0),
+            }
+        );
+    }
+}
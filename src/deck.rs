@@ -0,0 +1,201 @@
+use rand::thread_rng;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use crate::card::{Custom, Fact};
+use crate::error::{ErrorKind, Result};
+use crate::session::{self, TimeTables};
+
+/// The built-in deck
+pub const TABLES: &str = "tables";
+
+pub enum DeckCommand {
+    /// Reads a CSV of prompt,answer lines, replacing the deck of that name
+    Import {
+        file: PathBuf,
+        /// The file name by default
+        name: Option<String>,
+    },
+    List,
+    Remove {
+        name: String,
+    },
+}
+
+/// One line of an imported CSV
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct Entry {
+    prompt: String,
+    answer: String,
+}
+
+/// Imported decks are shared by all the profiles, like the assignments
+fn decks_dir() -> PathBuf {
+    session::data_dir().join("decks")
+}
+
+fn deck_path(name: &str) -> Result<PathBuf> {
+    session::check_name("deck", name)?;
+    Ok(decks_dir().join(format!("{}.json", name)))
+}
+
+fn not_found(name: &str, e: io::Error) -> ErrorKind {
+    match e.kind() {
+        io::ErrorKind::NotFound => ErrorKind::InvalidInput(format!("no deck named {}", name)),
+        _ => e.into(),
+    }
+}
+
+/// The fields of a CSV line. Fields may be quoted, with `""` for a quote.
+fn fields(line: &str) -> Vec<String> {
+    let mut fields = vec![String::new()];
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                fields.last_mut().unwrap().push('"');
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(String::new()),
+            c => fields.last_mut().unwrap().push(c),
+        }
+    }
+    fields
+        .iter()
+        .map(|field| field.trim().to_string())
+        .collect()
+}
+
+/// The answers are typed as numbers, so they must be whole numbers. A
+/// `prompt,answer` header line is skipped.
+fn parse(csv: &str) -> Result<Vec<Entry>> {
+    let mut entries = Vec::new();
+    let mut prompts = HashSet::new();
+    for (i, line) in csv.lines().enumerate() {
+        let invalid =
+            |reason: &str| ErrorKind::InvalidInput(format!("line {}: {}: {}", i + 1, reason, line));
+        let (prompt, answer) = match fields(line).as_slice() {
+            [field] if field.is_empty() => continue,
+            [prompt, _] if i == 0 && prompt.eq_ignore_ascii_case("prompt") => continue,
+            [prompt, answer] if !prompt.is_empty() => (prompt.clone(), answer.clone()),
+            _ => return Err(invalid("expected prompt,answer")),
+        };
+        let answer = answer
+            .parse::<u64>()
+            .map_err(|_| invalid("the answer is not a whole number"))?;
+        if !prompts.insert(prompt.clone()) {
+            return Err(invalid("duplicate prompt"));
+        }
+        entries.push(Entry {
+            prompt,
+            answer: answer.to_string(),
+        });
+    }
+    Ok(entries)
+}
+
+/// The facts of the deck `name`
+pub(crate) fn load(name: &str) -> Result<Vec<Fact>> {
+    if name == TABLES {
+        return Ok(TimeTables::gen(&mut thread_rng())
+            .into_iter()
+            .map(Fact::from)
+            .collect());
+    }
+    let json = fs::read_to_string(deck_path(name)?).map_err(|e| not_found(name, e))?;
+    let entries: Vec<Entry> = serde_json::from_str(&json)?;
+    Ok(entries
+        .into_iter()
+        .map(|Entry { prompt, answer }| {
+            Fact::Custom(Custom {
+                deck: name.to_string(),
+                prompt,
+                answer,
+            })
+        })
+        .collect())
+}
+
+pub fn deck(command: DeckCommand) -> Result<()> {
+    match command {
+        DeckCommand::Import { file, name } => {
+            let name = match name {
+                Some(name) => name,
+                None => file
+                    .file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .map(String::from)
+                    .ok_or_else(|| ErrorKind::InvalidInput("a deck name is needed".to_string()))?,
+            };
+            if name == TABLES {
+                return Err(ErrorKind::InvalidInput(format!(
+                    "{} is the name of the built-in deck",
+                    TABLES
+                )));
+            }
+            let path = deck_path(&name)?;
+            let entries = parse(&fs::read_to_string(file)?)?;
+            fs::create_dir_all(decks_dir())?;
+            fs::write(path, serde_json::to_string(&entries)?)?;
+            println!("Imported {} facts into {}", entries.len(), name);
+            Ok(())
+        }
+        DeckCommand::List => {
+            println!("{}: {} facts, built-in", TABLES, load(TABLES)?.len());
+            for deck in session::json_names(&decks_dir()) {
+                println!("{}: {} facts", deck, load(&deck)?.len());
+            }
+            Ok(())
+        }
+        DeckCommand::Remove { name } => {
+            fs::remove_file(deck_path(&name)?).map_err(|e| not_found(&name, e))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(prompt: &str, answer: &str) -> Entry {
+        Entry {
+            prompt: prompt.to_string(),
+            answer: answer.to_string(),
+        }
+    }
+
+    #[test]
+    fn deck_parse() {
+        let csv = "prompt,answer\n9²,81\n\n\"37 + ?, to 100\", 063\n";
+        assert_eq!(
+            parse(csv).unwrap(),
+            [entry("9²", "81"), entry("37 + ?, to 100", "63")]
+        );
+
+        assert!(parse("9²,81\n9²,81\n").is_err());
+        assert!(parse("half of 7,3.5\n").is_err());
+        assert!(parse("9²\n").is_err());
+    }
+    #[test]
+    fn deck_names() {
+        for name in ["../foo", "a/b", ""] {
+            assert!(matches!(deck_path(name), Err(ErrorKind::InvalidInput(_))));
+        }
+        assert!(deck_path("squares")
+            .unwrap()
+            .ends_with("decks/squares.json"));
+
+        let missing = io::Error::from(io::ErrorKind::NotFound);
+        assert!(matches!(
+            not_found("squares", missing),
+            ErrorKind::InvalidInput(_)
+        ));
+        let denied = io::Error::from(io::ErrorKind::PermissionDenied);
+        assert!(matches!(not_found("squares", denied), ErrorKind::Io(_)));
+    }
+}
@@ -0,0 +1,53 @@
+use serde::Serialize;
+
+use crate::card::Status;
+use crate::error::Result;
+use crate::forecast::session_length;
+use crate::session::Session;
+
+pub enum DueFormat {
+    /// Just the number
+    Plain,
+    Json,
+    /// For a shell prompt or a status bar, empty when nothing is due
+    Prompt,
+}
+
+pub struct DueOpts {
+    pub profile: String,
+    pub format: DueFormat,
+}
+
+#[derive(Serialize)]
+struct Due<'a> {
+    profile: &'a str,
+    /// Seen cards coming due in the next session
+    due: usize,
+    /// Cards never reviewed yet
+    new: usize,
+}
+
+/// Prints the number of cards due without touching the terminal or the
+/// profile, nor pulling the reviews of synced machines, so that it is cheap
+/// enough to run on every prompt. Returns whether any card is due.
+pub fn due(opts: DueOpts) -> Result<bool> {
+    let session = Session::load_local(&opts.profile)?;
+    let due = Due {
+        profile: &opts.profile,
+        due: session.due(session_length(&session.history)),
+        new: session
+            .cards
+            .iter()
+            .filter(|card| card.status == Status::Unseen)
+            .count(),
+    };
+
+    match opts.format {
+        DueFormat::Plain => println!("{}", due.due),
+        DueFormat::Json => println!("{}", serde_json::to_string(&due)?),
+        DueFormat::Prompt if due.due > 0 => println!("× {}", due.due),
+        DueFormat::Prompt => (),
+    }
+
+    Ok(due.due > 0)
+}
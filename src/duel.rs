@@ -0,0 +1,205 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::time::Duration;
+
+use crate::card::{Card, Fact, Factors, Rating};
+use crate::engine::{RatedCard, Summary};
+use crate::session::Session;
+
+pub struct Player {
+    pub profile: String,
+    pub session: Session,
+    pub summary: Summary,
+    /// Time spent answering, the tie-breaker
+    pub time: Duration,
+}
+
+impl Player {
+    pub fn new(profile: String, session: Session) -> Player {
+        Player {
+            profile,
+            session,
+            summary: Summary::new(),
+            time: Duration::default(),
+        }
+    }
+
+    fn cmp(&self, other: &Player) -> Ordering {
+        self.summary
+            .ok
+            .cmp(&other.summary.ok)
+            .then_with(|| other.time.cmp(&self.time))
+    }
+}
+
+/// Players take turns answering the same cards. Each answer is recorded in
+/// the session of the player who gave it.
+pub struct Duel {
+    pub players: Vec<Player>,
+    deck: Vec<Factors>,
+    turn: usize,
+}
+
+impl Duel {
+    pub fn new(players: Vec<Player>, deck: Vec<Factors>) -> Duel {
+        Duel {
+            players,
+            deck,
+            turn: 0,
+        }
+    }
+
+    /// The first `rounds` cards of a fresh deck shuffled from `seed`
+    pub fn deck(seed: u64, rounds: usize) -> Vec<Factors> {
+        let mut session = Session::new();
+        session.seed(seed);
+        session
+            .cards
+            .iter()
+            .filter_map(|card| card.value.factors())
+            .take(rounds)
+            .collect()
+    }
+
+    /// Whose turn it is, and the card to answer
+    pub fn current(&self) -> Option<(&Player, Factors)> {
+        let card = self.deck.get(self.turn / self.players.len())?;
+        Some((&self.players[self.turn % self.players.len()], *card))
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.current().is_none()
+    }
+
+    /// Grades `input` for the current player and passes the turn
+    pub fn answer(&mut self, input: &str, elapsed: Duration) -> Option<RatedCard> {
+        let value = self.current()?.1;
+        let count = self.players.len();
+        let player = &mut self.players[self.turn % count];
+
+        let answer = value.compute().to_string();
+        let rating = if input == answer {
+            player.summary.ok += 1;
+            Rating::Good
+        } else {
+            player.summary.ko += 1;
+            Rating::Bad
+        };
+        let card = player
+            .session
+            .cards
+            .iter()
+            .find(|card| card.value == Fact::Product(value))
+            .cloned()
+            .unwrap_or_else(|| Card::new(value.0, value.1));
+
+        player
+            .session
+            .review_fact(value.into(), rating, input, elapsed);
+        player.time += elapsed;
+        self.turn += 1;
+
+        Some(RatedCard {
+            card,
+            input: Some(input.to_string()),
+            answer,
+            rating,
+        })
+    }
+
+    /// The player with the most good answers, the fastest one on a tie
+    pub fn winner(&self) -> Option<&Player> {
+        let best = self.players.iter().max_by(|a, b| a.cmp(b))?;
+        let tied = self
+            .players
+            .iter()
+            .filter(|player| player.cmp(best) == Ordering::Equal)
+            .count();
+        Some(best).filter(|_| tied == 1)
+    }
+}
+
+impl fmt::Display for Duel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for player in &self.players {
+            writeln!(
+                f,
+                "{}: {} OK, {} KO in {}s",
+                player.profile,
+                player.summary.ok,
+                player.summary.ko,
+                player.time.as_secs()
+            )?;
+        }
+        match self.winner() {
+            Some(winner) => write!(f, "{} wins!", winner.profile),
+            None => write!(f, "Draw!"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn a_duel() -> Duel {
+        let players = vec![
+            Player::new("alice".to_string(), Session::new()),
+            Player::new("bob".to_string(), Session::new()),
+        ];
+        Duel::new(players, vec![Factors(7, 8), Factors(6, 9)])
+    }
+
+    #[test]
+    fn duel_turns() {
+        let mut duel = a_duel();
+        let second = Duration::from_secs(1);
+
+        assert_eq!(duel.current().unwrap().0.profile, "alice");
+        duel.answer("56", second);
+        let (player, card) = duel.current().unwrap();
+        assert_eq!((player.profile.as_str(), card), ("bob", Factors(7, 8)));
+        duel.answer("54", second);
+        assert_eq!(duel.current().unwrap().1, Factors(6, 9));
+        duel.answer("54", second);
+        duel.answer("54", second * 2);
+
+        assert!(duel.is_over());
+        assert_eq!(duel.answer("54", second), None);
+        assert_eq!(duel.players[0].summary, Summary { ok: 2, ko: 0 });
+        assert_eq!(duel.players[1].session.history.len(), 2);
+        assert_eq!(duel.players[1].session.history[0].rating, Rating::Bad);
+        assert_eq!(duel.winner().unwrap().profile, "alice");
+    }
+
+    #[test]
+    fn duel_tie() {
+        let mut duel = a_duel();
+        for _ in 0..4 {
+            duel.answer("0", Duration::from_secs(1));
+        }
+        assert!(duel.winner().is_none());
+        assert!(duel.to_string().ends_with("Draw!"));
+    }
+
+    #[test]
+    fn duel_deck() {
+        assert_eq!(Duel::deck(1, 5), Duel::deck(1, 5));
+        assert_eq!(Duel::deck(1, 5).len(), 5);
+    }
+
+    #[test]
+    fn duel_deck_ignores_the_answers() {
+        let deck = Duel::deck(20240131, 5);
+        let player = Player::new("alice".to_string(), Session::new());
+        let mut duel = Duel::new(vec![player], deck.clone());
+
+        let mut asked = Vec::new();
+        while let Some((_, card)) = duel.current() {
+            asked.push(card);
+            duel.answer("0", Duration::from_secs(1));
+        }
+        assert_eq!(asked, deck);
+        assert_eq!(duel.players[0].session.history.len(), 5);
+    }
+}
@@ -0,0 +1,347 @@
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use crate::card::{Card, Fact, Factors, Rating};
+use crate::error::Result;
+use crate::session::Session;
+
+/// An event coming from the player
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Action {
+    Input(String),
+    Review(Rating),
+    ShowAnswer,
+    Undo,
+    Exit,
+}
+
+/// Where actions come from: a keyboard, a script, a socket...
+pub trait Input {
+    fn read(&mut self, view: &View) -> Result<Action>;
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Summary {
+    pub ok: usize,
+    pub ko: usize,
+}
+
+impl Summary {
+    pub fn new() -> Self {
+        let ok = 0;
+        let ko = 0;
+        Summary { ok, ko }
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let plural = |x: usize| {
+            if x > 1 {
+                "(s)"
+            } else {
+                ""
+            }
+        };
+
+        write!(
+            f,
+            "Summary: {} OK{}; {} KO{}",
+            self.ok,
+            plural(self.ok),
+            self.ko,
+            plural(self.ko),
+        )
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RatedCard {
+    pub card: Card,
+    pub input: Option<String>,
+    pub answer: String,
+    pub rating: Rating,
+}
+
+impl fmt::Display for RatedCard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.rating, &self.input) {
+            (Rating::Good, _) => write!(f, "{} = {} OK", self.card.value, self.answer),
+            (Rating::Bad, Some(input)) => {
+                write!(f, "{} != {} KO => {}", self.card.value, input, self.answer)
+            }
+            (Rating::Bad, None) => write!(f, "{} = {} KO", self.card.value, self.answer),
+        }
+    }
+}
+
+/// Everything a frontend needs to draw the current screen
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct View {
+    /// The previous card and how it was rated
+    pub last_card: Option<RatedCard>,
+    /// The card to answer, `None` once the session is over
+    pub card: Option<Fact>,
+    /// A related fact to help with `card`
+    pub hint: Option<Factors>,
+    pub answer_visible: bool,
+    pub can_undo: bool,
+}
+
+/// The quiz logic, independent of any frontend: feed it actions with
+/// `update` and draw its `view`.
+pub struct State {
+    last_card: Option<RatedCard>,
+    answer_visible: bool,
+    current_card: Option<Card>,
+    started_at: Instant,
+    shown_at: Instant,
+    summary: Summary,
+    examination: bool,
+    hints: bool,
+    /// Reviews left before the session is over, if limited
+    remaining: Option<usize>,
+    /// When each card was answered, since the start of the session
+    laps: Vec<Duration>,
+}
+
+impl State {
+    pub fn new(session: &Session, examination: bool, hints: bool) -> State {
+        State {
+            last_card: None,
+            current_card: session.peek().cloned(),
+            started_at: Instant::now(),
+            shown_at: Instant::now(),
+            answer_visible: examination,
+            summary: Summary::new(),
+            examination,
+            hints,
+            remaining: None,
+            laps: Vec::new(),
+        }
+    }
+
+    /// Ends the session after `reviews` reviews
+    pub fn limit(&mut self, reviews: usize) {
+        self.remaining = Some(reviews);
+        if reviews == 0 {
+            self.current_card = None;
+        }
+    }
+
+    /// The next card, unless the limit is reached
+    fn next_card(&mut self, session: &Session) -> Option<Card> {
+        if let Some(remaining) = &mut self.remaining {
+            *remaining -= 1;
+            if *remaining == 0 {
+                return None;
+            }
+        }
+        session.peek().cloned()
+    }
+
+    pub fn view(&self) -> View {
+        let card = self.current_card.as_ref().map(|card| card.value.clone());
+        View {
+            last_card: self.last_card.clone(),
+            hint: card
+                .as_ref()
+                .and_then(Fact::factors)
+                .filter(|_| self.hints)
+                .map(|value| value.neighbour()),
+            card,
+            answer_visible: self.answer_visible,
+            can_undo: self.last_card.is_some(),
+        }
+    }
+
+    pub fn summary(&self) -> &Summary {
+        &self.summary
+    }
+
+    /// Time since the start of the session
+    pub fn elapsed(&self) -> Duration {
+        self.started_at.elapsed()
+    }
+
+    pub fn laps(&self) -> &[Duration] {
+        &self.laps
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.current_card.is_none()
+    }
+
+    fn show_answer(&mut self) {
+        if !self.examination {
+            self.answer_visible = true
+        }
+    }
+
+    fn hide_answer(&mut self) {
+        if !self.examination {
+            self.answer_visible = false
+        }
+    }
+
+    pub fn update(&mut self, session: &mut Session, action: Action) {
+        if let Some(card) = &self.current_card {
+            match action {
+                Action::Input(input) => {
+                    let expected = card.value.answer();
+                    let rating = if input == expected {
+                        self.summary.ok += 1;
+                        Rating::Good
+                    } else {
+                        self.summary.ko += 1;
+                        Rating::Bad
+                    };
+
+                    session.review_answer(rating, &input, self.shown_at.elapsed());
+                    self.laps.push(self.elapsed());
+                    self.last_card = Some(RatedCard {
+                        card: card.to_owned(),
+                        rating,
+                        input: Some(input),
+                        answer: expected,
+                    });
+                    self.current_card = self.next_card(session);
+                    self.shown_at = Instant::now();
+                    self.hide_answer();
+                }
+                Action::Review(rating) => {
+                    session.review(rating);
+                    self.laps.push(self.elapsed());
+                    match rating {
+                        Rating::Good => self.summary.ok += 1,
+                        Rating::Bad => self.summary.ko += 1,
+                    }
+                    self.last_card = Some(RatedCard {
+                        card: card.to_owned(),
+                        rating,
+                        input: None,
+                        answer: card.value.answer(),
+                    });
+                    self.current_card = self.next_card(session);
+                    self.shown_at = Instant::now();
+                    self.hide_answer()
+                }
+                Action::ShowAnswer => self.show_answer(),
+                Action::Undo => {
+                    session.rollback();
+                    if let Some(last_card) = &self.last_card {
+                        match last_card.rating {
+                            Rating::Good => self.summary.ok -= 1,
+                            Rating::Bad => self.summary.ko -= 1,
+                        }
+                        if let Some(remaining) = &mut self.remaining {
+                            *remaining += 1;
+                        }
+                        self.laps.pop();
+                    }
+                    self.last_card = None;
+                    self.show_answer();
+                    self.current_card = session.peek().cloned();
+                    self.shown_at = Instant::now();
+                }
+                Action::Exit => self.current_card = None,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::card::Status;
+
+    fn a_session() -> Session {
+        Session::from(vec![Card::new(7, 8), Card::new(6, 9)])
+    }
+
+    #[test]
+    fn state_input() {
+        let mut session = a_session();
+        let mut state = State::new(&session, false, false);
+        let first = state.view().card.unwrap();
+        assert!(!state.view().can_undo);
+
+        state.update(&mut session, Action::Input("0".to_string()));
+
+        let view = state.view();
+        let last_card = view.last_card.unwrap();
+        assert_eq!(last_card.card.value, first);
+        assert_eq!(last_card.rating, Rating::Bad);
+        assert_eq!(last_card.input.as_deref(), Some("0"));
+        assert_ne!(view.card, Some(first));
+        assert!(view.can_undo);
+        assert_eq!(state.summary(), &Summary { ok: 0, ko: 1 });
+    }
+
+    #[test]
+    fn state_input_reviews_the_card_on_screen() {
+        let mut session = a_session();
+        let mut state = State::new(&session, false, false);
+
+        for _ in 0..2 {
+            let shown = state.view().card.unwrap();
+            state.update(&mut session, Action::Input("0".to_string()));
+            assert_eq!(session.history.last().unwrap().value, shown);
+            assert_eq!(
+                state.view().card.as_ref(),
+                session.peek().map(|card| &card.value)
+            );
+        }
+    }
+
+    #[test]
+    fn state_undo() {
+        let mut session = a_session();
+        let mut state = State::new(&session, false, false);
+        let first = state.view().card.unwrap();
+
+        state.update(&mut session, Action::Review(Rating::Good));
+        state.update(&mut session, Action::Undo);
+
+        let view = state.view();
+        assert_eq!(view.card, Some(first));
+        assert!(view.answer_visible);
+        assert_eq!(view.last_card, None);
+        assert_eq!(state.summary(), &Summary { ok: 0, ko: 0 });
+        assert!(session
+            .cards
+            .iter()
+            .all(|card| card.status == Status::Unseen));
+    }
+
+    #[test]
+    fn state_hints_and_exit() {
+        let mut session = a_session();
+        let mut state = State::new(&session, false, true);
+        let card = state.view().card.unwrap();
+        assert_eq!(
+            state.view().hint,
+            card.factors().map(|card| card.neighbour())
+        );
+
+        state.update(&mut session, Action::Exit);
+        assert!(state.is_over());
+        assert_eq!(state.view().hint, None);
+    }
+
+    #[test]
+    fn state_limit() {
+        let mut session = a_session();
+        let mut state = State::new(&session, false, false);
+        state.limit(2);
+
+        state.update(&mut session, Action::Review(Rating::Bad));
+        state.update(&mut session, Action::Undo);
+        state.update(&mut session, Action::Review(Rating::Good));
+        assert!(!state.is_over());
+        state.update(&mut session, Action::Review(Rating::Good));
+        assert!(state.is_over());
+        assert_eq!(state.summary(), &Summary { ok: 2, ko: 0 });
+        assert_eq!(state.laps().len(), 2);
+    }
+}
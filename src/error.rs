@@ -5,19 +5,40 @@ pub type Result<T> = std::result::Result<T, ErrorKind>;
 
 #[derive(Debug)]
 pub enum ErrorKind {
+    #[cfg(feature = "cli")]
     Crossterm(crossterm::ErrorKind),
+    #[cfg(feature = "anki")]
+    Sqlite(rusqlite::Error),
+    #[cfg(feature = "anki")]
+    Zip(zip::result::ZipError),
     Serde(serde_json::Error),
     Io(io::Error),
     InvalidAnswer(ParseIntError),
+    InvalidInput(String),
     Exit,
 }
 
+#[cfg(feature = "cli")]
 impl From<crossterm::ErrorKind> for ErrorKind {
     fn from(err: crossterm::ErrorKind) -> ErrorKind {
         ErrorKind::Crossterm(err)
     }
 }
 
+#[cfg(feature = "anki")]
+impl From<rusqlite::Error> for ErrorKind {
+    fn from(err: rusqlite::Error) -> ErrorKind {
+        ErrorKind::Sqlite(err)
+    }
+}
+
+#[cfg(feature = "anki")]
+impl From<zip::result::ZipError> for ErrorKind {
+    fn from(err: zip::result::ZipError) -> ErrorKind {
+        ErrorKind::Zip(err)
+    }
+}
+
 impl From<serde_json::Error> for ErrorKind {
     fn from(err: serde_json::Error) -> ErrorKind {
         ErrorKind::Serde(err)
@@ -0,0 +1,84 @@
+use chrono::NaiveDate;
+use std::collections::BTreeSet;
+
+use crate::history::{self, Review};
+use crate::session::Session;
+
+/// Used when the profile has no history to estimate a session length from
+const DEFAULT_SESSION_LENGTH: u32 = 20;
+const CHART_WIDTH: usize = 30;
+
+pub enum Period {
+    Sessions(usize),
+    Days(usize),
+}
+
+pub struct ForecastOpts {
+    pub profile: String,
+    pub period: Period,
+    /// Number of reviews per session, estimated from the history if not set
+    pub length: Option<u32>,
+}
+
+/// Average number of reviews per session
+pub(crate) fn session_length(history: &[Review]) -> u32 {
+    let sessions = history::sessions(history);
+    match sessions.len() {
+        0 => DEFAULT_SESSION_LENGTH,
+        n => (history.len() as f64 / n as f64).round().max(1.0) as u32,
+    }
+}
+
+/// Average number of sessions per day of practice
+pub(crate) fn sessions_per_day(history: &[Review]) -> usize {
+    let dates: BTreeSet<NaiveDate> = history.iter().map(Review::date).collect();
+    match dates.len() {
+        0 => 1,
+        n => (history::sessions(history).len() as f64 / n as f64)
+            .round()
+            .max(1.0) as usize,
+    }
+}
+
+fn print_forecast(rows: &[(String, usize)], length: u32) {
+    let max = rows.iter().map(|(_, count)| *count).max().unwrap_or(0);
+    let width = rows.iter().map(|(label, _)| label.len()).max().unwrap_or(0);
+
+    for (label, count) in rows {
+        let bar = "█".repeat((count * CHART_WIDTH).checked_div(max).unwrap_or(0));
+        println!("{:width$} {} {}", label, bar, count, width = width);
+    }
+    println!();
+    println!(
+        "Cards coming due, assuming {} reviews per session and that every answer is good",
+        length
+    );
+}
+
+pub fn forecast(opts: ForecastOpts) {
+    let session = Session::load(&opts.profile);
+    let length = opts
+        .length
+        .unwrap_or_else(|| session_length(&session.history))
+        .max(1);
+
+    let rows: Vec<(String, usize)> = match opts.period {
+        Period::Sessions(sessions) => session
+            .forecast(length, sessions)
+            .into_iter()
+            .enumerate()
+            .map(|(i, count)| (format!("Session {}", i + 1), count))
+            .collect(),
+        Period::Days(days) => {
+            let per_day = sessions_per_day(&session.history);
+            session
+                .forecast(length, days.saturating_mul(per_day))
+                .chunks(per_day)
+                .enumerate()
+                .map(|(i, counts)| (format!("Day {}", i + 1), counts.iter().sum()))
+                .collect()
+        }
+    };
+
+    print_forecast(&rows, length);
+}
@@ -0,0 +1,102 @@
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::session::now;
+
+/// A finished sprint, kept in the profile to race against later. The ghost
+/// cannot be derived from the review history: it does not record which
+/// reviews were part of a sprint, and times them to the second only.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct Sprint {
+    /// Seconds since the unix epoch
+    pub time: u64,
+    /// When each card was answered, in milliseconds since the start of the
+    /// sprint
+    pub offsets: Vec<u64>,
+}
+
+impl Sprint {
+    /// From the time of each answer, as the player is timed during the race
+    pub fn new(laps: &[Duration]) -> Sprint {
+        Sprint {
+            time: now().unwrap_or_default(),
+            offsets: laps.iter().map(|lap| lap.as_millis() as u64).collect(),
+        }
+    }
+}
+
+/// The fastest previous sprint of a given length, replayed against the
+/// player
+#[derive(Debug, PartialEq, Eq)]
+pub struct Ghost {
+    /// When each card of the run was answered, since its start
+    offsets: Vec<Duration>,
+}
+
+impl Ghost {
+    /// The fastest of the `sprints` of `length` cards, if any
+    pub fn best(sprints: &[Sprint], length: usize) -> Option<Ghost> {
+        sprints
+            .iter()
+            .filter(|sprint| length > 0 && sprint.offsets.len() == length)
+            .min_by_key(|sprint| sprint.offsets[length - 1])
+            .map(|sprint| Ghost {
+                offsets: sprint
+                    .offsets
+                    .iter()
+                    .map(|&offset| Duration::from_millis(offset))
+                    .collect(),
+            })
+    }
+
+    /// Number of cards the ghost had answered `elapsed` into its run
+    pub fn progress(&self, elapsed: Duration) -> usize {
+        self.offsets
+            .iter()
+            .take_while(|&&offset| offset <= elapsed)
+            .count()
+    }
+
+    pub fn time(&self) -> Duration {
+        self.offsets[self.offsets.len() - 1]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn a_sprint(offsets: &[u64]) -> Sprint {
+        Sprint {
+            time: 0,
+            offsets: offsets.to_vec(),
+        }
+    }
+
+    #[test]
+    fn ghost_best() {
+        let sprints = vec![
+            a_sprint(&[3000, 4000, 6000]),
+            a_sprint(&[1000, 2000]),
+            a_sprint(&[2000, 3000, 5000]),
+        ];
+
+        let ghost = Ghost::best(&sprints, 3).unwrap();
+        assert_eq!(ghost.time(), Duration::from_secs(5));
+        assert_eq!(ghost.progress(Duration::from_secs(4)), 2);
+
+        let ghost = Ghost::best(&sprints, 2).unwrap();
+        assert_eq!(ghost.time(), Duration::from_secs(2));
+        assert_eq!(ghost.progress(Duration::from_millis(500)), 0);
+
+        assert_eq!(Ghost::best(&sprints, 4), None);
+        assert_eq!(Ghost::best(&sprints, 0), None);
+        assert_eq!(Ghost::best(&[a_sprint(&[])], 0), None);
+    }
+
+    #[test]
+    fn ghost_sprint() {
+        let laps = [Duration::from_millis(1500), Duration::from_secs(3)];
+        assert_eq!(Sprint::new(&laps).offsets, [1500, 3000]);
+    }
+}
@@ -0,0 +1,309 @@
+use chrono::{Duration, Local, NaiveDate, TimeZone};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use crate::card::{Fact, Factors, Rating};
+
+/// Reviews further apart than this, in seconds, belong to different sessions
+const SESSION_GAP: u64 = 30 * 60;
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Serialize, Deserialize)]
+pub struct Review {
+    pub value: Fact,
+    pub rating: Rating,
+    /// Seconds since the unix epoch
+    pub time: u64,
+    /// Time taken to answer, in milliseconds
+    #[serde(default)]
+    pub duration: Option<u64>,
+    /// The typed answer, if any
+    #[serde(default)]
+    pub input: Option<String>,
+}
+
+/// An assignment done by the student
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct Completion {
+    pub assignment: String,
+    /// Seconds since the unix epoch
+    pub time: u64,
+    pub good: usize,
+    pub bad: usize,
+}
+
+/// A wrong answer that is the product of another fact
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Confusion {
+    pub value: Factors,
+    pub answer: u8,
+    pub count: usize,
+}
+
+impl Confusion {
+    /// The facts `value` was mixed up with
+    pub fn partners(&self) -> Vec<Factors> {
+        Factors::with_product(self.answer)
+            .into_iter()
+            .filter(|&partner| partner != self.value)
+            .collect()
+    }
+}
+
+impl Review {
+    /// Local date at which the review happened
+    pub fn date(&self) -> NaiveDate {
+        Local
+            .timestamp_opt(self.time as i64, 0)
+            .single()
+            .map(|time| time.date_naive())
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct Stats {
+    pub good: usize,
+    pub bad: usize,
+}
+
+impl Stats {
+    pub fn add(&mut self, rating: Rating) {
+        match rating {
+            Rating::Good => self.good += 1,
+            Rating::Bad => self.bad += 1,
+        }
+    }
+
+    pub fn reviews(&self) -> usize {
+        self.good + self.bad
+    }
+
+    pub fn accuracy(&self) -> Option<f64> {
+        match self.reviews() {
+            0 => None,
+            reviews => Some(self.good as f64 / reviews as f64),
+        }
+    }
+}
+
+pub fn stats_by_fact(history: &[Review]) -> HashMap<Fact, Stats> {
+    let mut stats: HashMap<Fact, Stats> = HashMap::new();
+    for review in history {
+        stats
+            .entry(review.value.clone())
+            .or_default()
+            .add(review.rating);
+    }
+    stats
+}
+
+/// Number of times each fact was answered wrong, not counting the first time
+/// it was seen. Same as `Card::lapses`.
+pub fn lapses_by_fact(history: &[Review]) -> HashMap<Fact, usize> {
+    let mut seen: HashSet<&Fact> = HashSet::new();
+    let mut lapses: HashMap<Fact, usize> = HashMap::new();
+    for review in history {
+        let first = seen.insert(&review.value);
+        if review.rating == Rating::Bad && !first {
+            *lapses.entry(review.value.clone()).or_default() += 1;
+        }
+    }
+    lapses
+}
+
+/// Wrong answers that match another fact of the table, most frequent first
+pub fn confusions(history: &[Review]) -> Vec<Confusion> {
+    let mut counts: HashMap<(Factors, u8), usize> = HashMap::new();
+    for review in history {
+        let answer = match (&review.rating, &review.input, review.value.factors()) {
+            (Rating::Bad, Some(input), Some(value)) => {
+                input.parse::<u8>().ok().map(|answer| (value, answer))
+            }
+            _ => None,
+        };
+        if let Some((value, answer)) = answer {
+            if answer != value.compute() && !Factors::with_product(answer).is_empty() {
+                *counts.entry((value, answer)).or_default() += 1;
+            }
+        }
+    }
+
+    let mut confusions: Vec<Confusion> = counts
+        .into_iter()
+        .map(|((value, answer), count)| Confusion {
+            value,
+            answer,
+            count,
+        })
+        .collect();
+    confusions.sort_by_key(|c| (Reverse(c.count), c.value.0, c.value.1, c.answer));
+    confusions
+}
+
+/// Splits the history into practice sessions
+pub fn sessions(history: &[Review]) -> Vec<&[Review]> {
+    let mut sessions = Vec::new();
+    let mut start = 0;
+    for i in 1..history.len() {
+        if history[i].time.saturating_sub(history[i - 1].time) > SESSION_GAP {
+            sessions.push(&history[start..i]);
+            start = i;
+        }
+    }
+    if start < history.len() {
+        sessions.push(&history[start..]);
+    }
+    sessions
+}
+
+/// The reviews of both logs, without the ones they share, in an order that
+/// does not depend on which log comes first
+pub fn merge(a: &[Review], b: &[Review]) -> Vec<Review> {
+    let known: HashSet<&Review> = a.iter().collect();
+    let mut merged: Vec<Review> = a
+        .iter()
+        .chain(b.iter().filter(|review| !known.contains(review)))
+        .cloned()
+        .collect();
+    merged.sort_by_key(|review| {
+        (
+            review.time,
+            review.value.clone(),
+            review.rating == Rating::Good,
+            review.duration,
+            review.input.clone(),
+        )
+    });
+    merged
+}
+
+/// Number of consecutive days with at least one review, ending today, or
+/// yesterday if there was no practice yet today.
+pub fn streak(dates: &BTreeSet<NaiveDate>, today: NaiveDate) -> usize {
+    let mut day = if dates.contains(&today) {
+        today
+    } else {
+        today - Duration::days(1)
+    };
+
+    let mut streak = 0;
+    while dates.contains(&day) {
+        streak += 1;
+        day -= Duration::days(1);
+    }
+    streak
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fact(x: u8, y: u8) -> Fact {
+        Factors(x, y).into()
+    }
+
+    fn a_review(x: u8, y: u8, rating: Rating) -> Review {
+        Review {
+            value: fact(x, y),
+            rating,
+            time: 0,
+            duration: None,
+            input: None,
+        }
+    }
+
+    #[test]
+    fn stats_by_fact_accuracy() {
+        let stats = stats_by_fact(&[
+            a_review(7, 8, Rating::Bad),
+            a_review(7, 8, Rating::Good),
+            a_review(7, 8, Rating::Good),
+            a_review(7, 8, Rating::Good),
+            a_review(2, 2, Rating::Good),
+        ]);
+
+        assert_eq!(stats[&fact(7, 8)], Stats { good: 3, bad: 1 });
+        assert_eq!(stats[&fact(7, 8)].accuracy(), Some(0.75));
+        assert_eq!(stats[&fact(2, 2)].accuracy(), Some(1.0));
+        assert_eq!(Stats::default().accuracy(), None);
+    }
+
+    #[test]
+    fn lapses_by_fact_after_first_review() {
+        let lapses = lapses_by_fact(&[
+            a_review(2, 2, Rating::Good),
+            a_review(7, 8, Rating::Bad),
+            a_review(7, 8, Rating::Good),
+            a_review(7, 8, Rating::Bad),
+            a_review(7, 8, Rating::Bad),
+            a_review(6, 9, Rating::Bad),
+        ]);
+
+        assert_eq!(lapses.get(&fact(7, 8)), Some(&2));
+        assert_eq!(lapses.get(&fact(2, 2)), None);
+        assert_eq!(lapses.get(&fact(6, 9)), None);
+    }
+
+    #[test]
+    fn streak_ending_today_or_yesterday() {
+        let day = |d| NaiveDate::from_ymd_opt(2021, 3, d).unwrap();
+        let dates: BTreeSet<NaiveDate> = [1, 3, 4, 5].iter().map(|&d| day(d)).collect();
+
+        assert_eq!(streak(&dates, day(5)), 3);
+        assert_eq!(streak(&dates, day(6)), 3);
+        assert_eq!(streak(&dates, day(7)), 0);
+        assert_eq!(streak(&dates, day(2)), 1);
+    }
+
+    #[test]
+    fn confusions_from_wrong_answers() {
+        let wrong = |x, y, input: &str| Review {
+            input: Some(input.to_string()),
+            ..a_review(x, y, Rating::Bad)
+        };
+        let confusions = confusions(&[
+            wrong(7, 8, "54"),
+            wrong(7, 8, "54"),
+            wrong(7, 8, "55"),
+            wrong(3, 4, "16"),
+            a_review(7, 8, Rating::Bad),
+        ]);
+
+        assert_eq!(
+            confusions,
+            vec![
+                Confusion {
+                    value: Factors(7, 8),
+                    answer: 54,
+                    count: 2
+                },
+                Confusion {
+                    value: Factors(3, 4),
+                    answer: 16,
+                    count: 1
+                },
+            ]
+        );
+        assert_eq!(confusions[0].partners(), vec![Factors(6, 9), Factors(9, 6)]);
+        assert_eq!(
+            confusions[1].partners(),
+            vec![Factors(2, 8), Factors(4, 4), Factors(8, 2)]
+        );
+    }
+
+    #[test]
+    fn sessions_split_on_gap() {
+        let at = |time| Review {
+            time,
+            ..a_review(2, 2, Rating::Good)
+        };
+        let history = [at(0), at(10), at(11 + SESSION_GAP), at(71 + SESSION_GAP)];
+        let sessions = sessions(&history);
+
+        assert_eq!(sessions.len(), 2);
+        assert_eq!(sessions[0].len(), 2);
+        assert_eq!(sessions[1].len(), 2);
+        assert!(super::sessions(&[]).is_empty());
+    }
+}
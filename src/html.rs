@@ -0,0 +1,277 @@
+use chrono::{Local, TimeZone};
+use std::fmt::Write;
+
+use crate::card::Factors;
+use crate::history::{self, Review, Stats};
+use crate::session::{Session, TABLES};
+use crate::stats::{Dashboard, Day, Lapses};
+
+const TOP_CONFUSIONS: usize = 5;
+
+const STYLE: &str = "
+body { font-family: sans-serif; margin: 2em auto; max-width: 50em; color: #222; }
+h1, h2 { font-weight: normal; }
+table { border-collapse: collapse; margin-bottom: 2em; }
+th, td { padding: 0.3em 0.6em; text-align: right; }
+.heatmap td { width: 3.5em; height: 2.5em; text-align: center; border: 1px solid #fff; }
+.history td, .history th { border-bottom: 1px solid #ddd; }
+.unseen { background: #eee; color: #999; }
+";
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn percent(accuracy: Option<f64>) -> String {
+    accuracy
+        .map(|accuracy| format!("{:.0}%", accuracy * 100.0))
+        .unwrap_or_else(|| "-".to_string())
+}
+
+/// Each fact colored from red to green by accuracy
+fn heatmap(out: &mut String, session: &Session) {
+    let stats = history::stats_by_fact(&session.history);
+
+    out.push_str("<table class=\"heatmap\"><tr><th>&times;</th>");
+    TABLES.for_each(|y| write!(out, "<th>{}</th>", y).unwrap());
+    out.push_str("</tr>");
+
+    for x in TABLES {
+        write!(out, "<tr><th>{}</th>", x).unwrap();
+        for y in TABLES {
+            let value = Factors(x, y);
+            match stats.get(&value.into()).and_then(Stats::accuracy) {
+                Some(accuracy) => write!(
+                    out,
+                    "<td style=\"background: hsl({:.0}, 70%, 65%)\" title=\"{}\">{}</td>",
+                    accuracy * 120.0,
+                    percent(Some(accuracy)),
+                    value.compute()
+                )
+                .unwrap(),
+                None => write!(out, "<td class=\"unseen\">{}</td>", value.compute()).unwrap(),
+            }
+        }
+        out.push_str("</tr>");
+    }
+    out.push_str("</table>");
+}
+
+fn accuracy_chart(out: &mut String, days: &[Day]) {
+    let (width, height) = (600.0, 200.0);
+    let points: Vec<(usize, f64)> = days
+        .iter()
+        .enumerate()
+        .filter_map(|(i, day)| day.accuracy.map(|accuracy| (i, accuracy)))
+        .collect();
+
+    if points.is_empty() {
+        out.push_str("<p>No reviews yet</p>");
+        return;
+    }
+
+    let step = width / (days.len().max(2) - 1) as f64;
+    let coords: Vec<(f64, f64)> = points
+        .iter()
+        .map(|&(i, accuracy)| (i as f64 * step, height - accuracy * height))
+        .collect();
+    let polyline: Vec<String> = coords
+        .iter()
+        .map(|(x, y)| format!("{:.1},{:.1}", x, y))
+        .collect();
+
+    write!(
+        out,
+        "<svg width=\"{w}\" height=\"{h}\" viewBox=\"-10 -10 {vw} {vh}\">\
+         <line x1=\"0\" y1=\"{h}\" x2=\"{w}\" y2=\"{h}\" stroke=\"#999\"/>\
+         <line x1=\"0\" y1=\"0\" x2=\"{w}\" y2=\"0\" stroke=\"#ddd\"/>\
+         <polyline fill=\"none\" stroke=\"#2a7\" stroke-width=\"2\" points=\"{p}\"/>",
+        w = width,
+        h = height,
+        vw = width + 20.0,
+        vh = height + 20.0,
+        p = polyline.join(" ")
+    )
+    .unwrap();
+    for (x, y) in &coords {
+        write!(
+            out,
+            "<circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"3\" fill=\"#2a7\"/>",
+            x, y
+        )
+        .unwrap();
+    }
+    out.push_str("</svg>");
+    if let (Some(first), Some(last)) = (days.first(), days.last()) {
+        write!(out, "<p>{} &ndash; {}</p>", first.date, last.date).unwrap();
+    }
+}
+
+fn problem_facts(out: &mut String, lapses: &[Lapses]) {
+    if lapses.is_empty() {
+        out.push_str("<p>None</p>");
+        return;
+    }
+
+    out.push_str("<table class=\"history\"><tr><th>Fact</th><th>Lapses</th></tr>");
+    for Lapses { x, y, lapses } in lapses {
+        write!(
+            out,
+            "<tr><td>{} &times; {} = {}</td><td>{}</td></tr>",
+            x,
+            y,
+            x * y,
+            lapses
+        )
+        .unwrap();
+    }
+    out.push_str("</table>");
+}
+
+fn confusions(out: &mut String, history: &[Review]) {
+    let confusions = history::confusions(history);
+    if confusions.is_empty() {
+        out.push_str("<p>None</p>");
+        return;
+    }
+
+    out.push_str(
+        "<table class=\"history\"><tr><th>Fact</th><th>Answered</th>\
+         <th>Mixed up with</th><th>Times</th></tr>",
+    );
+    for confusion in confusions.iter().take(TOP_CONFUSIONS) {
+        let partners: Vec<String> = confusion
+            .partners()
+            .iter()
+            .map(|Factors(x, y)| format!("{} &times; {}", x, y))
+            .collect();
+        write!(
+            out,
+            "<tr><td>{} &times; {}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+            confusion.value.0,
+            confusion.value.1,
+            confusion.answer,
+            partners.join(", "),
+            confusion.count
+        )
+        .unwrap();
+    }
+    out.push_str("</table>");
+}
+
+fn session_history(out: &mut String, history: &[Review]) {
+    let sessions = history::sessions(history);
+    if sessions.is_empty() {
+        out.push_str("<p>None</p>");
+        return;
+    }
+
+    out.push_str(
+        "<table class=\"history\"><tr><th>Date</th><th>Cards</th>\
+         <th>OK</th><th>KO</th><th>Accuracy</th></tr>",
+    );
+    for reviews in sessions.iter().rev() {
+        let mut stats = Stats::default();
+        reviews.iter().for_each(|review| stats.add(review.rating));
+        let start = Local
+            .timestamp_opt(reviews[0].time as i64, 0)
+            .single()
+            .map(|time| time.format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_default();
+
+        write!(
+            out,
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+            start,
+            stats.reviews(),
+            stats.good,
+            stats.bad,
+            percent(stats.accuracy())
+        )
+        .unwrap();
+    }
+    out.push_str("</table>");
+}
+
+/// A standalone HTML page, with no external assets
+pub fn render(profile: &str, session: &Session) -> String {
+    let dashboard = Dashboard::new(session, Local::now().date_naive());
+    let mut out = String::new();
+
+    write!(
+        out,
+        "<!DOCTYPE html><html><head><meta charset=\"utf-8\">\
+         <title>multa - {profile}</title><style>{style}</style></head><body>\
+         <h1>Progress report: {profile}</h1>",
+        profile = escape(profile),
+        style = STYLE
+    )
+    .unwrap();
+    write!(
+        out,
+        "<p>{} unseen; {} learning; {} learned. Streak: {} day(s)</p>",
+        dashboard.status.unseen,
+        dashboard.status.learning,
+        dashboard.status.learned,
+        dashboard.streak
+    )
+    .unwrap();
+
+    out.push_str("<h2>Times table</h2>");
+    heatmap(&mut out, session);
+    out.push_str("<h2>Accuracy over time</h2>");
+    accuracy_chart(&mut out, &dashboard.days);
+    out.push_str("<h2>Problem facts</h2>");
+    problem_facts(&mut out, &dashboard.lapses);
+    out.push_str("<h2>Confusions</h2>");
+    confusions(&mut out, &session.history);
+    out.push_str("<h2>Sessions</h2>");
+    session_history(&mut out, &session.history);
+    out.push_str("</body></html>\n");
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::card::{Card, Rating};
+
+    fn a_review(value: Factors, rating: Rating, time: u64) -> Review {
+        Review {
+            value: value.into(),
+            rating,
+            time,
+            duration: None,
+            input: None,
+        }
+    }
+
+    #[test]
+    fn html_render() {
+        let mut session = Session::from(vec![Card::new(7, 8), Card::new(6, 9)]);
+        session.history = vec![
+            a_review(Factors(7, 8), Rating::Good, 1_600_000_000),
+            a_review(Factors(7, 8), Rating::Bad, 1_600_000_010),
+            a_review(Factors(6, 9), Rating::Good, 1_600_000_020),
+        ];
+        let html = render("<me>", &session);
+
+        assert!(html.contains("<h1>Progress report: &lt;me&gt;</h1>"));
+        let heatmap = html.split("class=\"heatmap\"").nth(1).unwrap();
+        let heatmap = &heatmap[..heatmap.find("</table>").unwrap()];
+        let size = TABLES.len();
+        assert_eq!(heatmap.matches("<tr>").count(), size + 1);
+        assert_eq!(heatmap.matches("<td").count(), size * size);
+        assert_eq!(heatmap.matches("class=\"unseen\"").count(), size * size - 2);
+        assert!(heatmap.contains("hsl(60, 70%, 65%)\" title=\"50%\">56</td>"));
+        assert!(heatmap.contains("hsl(120, 70%, 65%)\" title=\"100%\">54</td>"));
+
+        let sessions = html.split("<h2>Sessions</h2>").nth(1).unwrap();
+        assert_eq!(sessions.matches("<tr>").count(), 2);
+        assert!(sessions.contains("<td>3</td><td>2</td><td>1</td><td>67%</td></tr>"));
+    }
+}
@@ -0,0 +1,137 @@
+use std::fs::{self, File, TryLockError};
+
+use crate::error::{ErrorKind, Result};
+use crate::history;
+use crate::session::{self, Merge, Session};
+
+/// What to do when the profile is already open in another multa
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Conflict {
+    Refuse,
+    /// Practice without saving
+    ReadOnly,
+    /// Save the answers along with the ones saved meanwhile by the other
+    /// multa
+    #[default]
+    Merge,
+}
+
+/// A profile opened for practice. The lock is released when dropped, or
+/// when the process dies.
+pub(crate) struct Profile {
+    pub name: String,
+    /// None when another multa holds it
+    lock: Option<File>,
+    conflict: Conflict,
+}
+
+impl Profile {
+    /// Tells the player when another multa holds the profile
+    pub fn open(name: &str, conflict: Conflict) -> Result<Profile> {
+        let profile = Profile::open_quietly(name, conflict)?;
+        if let Some(notice) = profile.notice() {
+            println!("{}", notice);
+        }
+        Ok(profile)
+    }
+
+    /// Like `open`, for when the standard output is not the player's
+    pub fn open_quietly(name: &str, conflict: Conflict) -> Result<Profile> {
+        session::check_name("profile", name)?;
+        let dir = session::data_dir().join("locks");
+        fs::create_dir_all(&dir)?;
+        let file = File::create(dir.join(name))?;
+        let lock = match file.try_lock() {
+            Ok(()) => Some(file),
+            Err(TryLockError::WouldBlock) => None,
+            Err(TryLockError::Error(e)) => return Err(e.into()),
+        };
+
+        if lock.is_none() && conflict == Conflict::Refuse {
+            return Err(ErrorKind::InvalidInput(format!(
+                "{} is already open in another multa",
+                name
+            )));
+        }
+
+        Ok(Profile {
+            name: name.to_string(),
+            lock,
+            conflict,
+        })
+    }
+
+    /// What happens to the session, when another multa holds the profile
+    pub fn notice(&self) -> Option<String> {
+        if self.lock.is_some() {
+            return None;
+        }
+        match self.conflict {
+            Conflict::Refuse => None,
+            Conflict::ReadOnly => Some(format!(
+                "{} is open in another multa, this session will not be saved",
+                self.name
+            )),
+            Conflict::Merge => Some(format!(
+                "{} is open in another multa, both sessions will be merged",
+                self.name
+            )),
+        }
+    }
+
+    pub fn load(&self) -> Session {
+        Session::load(&self.name)
+    }
+
+    /// Keeps the answers saved meanwhile by another multa, even when it
+    /// was the one waiting for the lock
+    pub fn save(&self, session: &Session) -> Result<()> {
+        if self.lock.is_none() && self.conflict == Conflict::ReadOnly {
+            return Ok(());
+        }
+        match merge(self.load(), session)? {
+            Some(merged) => merged.save(&self.name),
+            None => session.save(&self.name),
+        }
+    }
+}
+
+/// `session` with the reviews of `saved` it does not have, if any
+fn merge(mut saved: Session, session: &Session) -> Result<Option<Session>> {
+    if history::merge(&session.history, &saved.history).len() == session.history.len() {
+        return Ok(None);
+    }
+    saved.merge(Session::from_json(&session.to_json()?)?, Merge::Replay);
+    Ok(Some(saved))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::card::{Card, Factors, Rating};
+    use std::time::Duration;
+
+    #[test]
+    fn lock_merge() {
+        let second = Duration::from_secs(1);
+        let mut saved = Session::from(vec![Card::new(7, 8), Card::new(6, 9)]);
+        saved.review_fact(Factors(7, 8).into(), Rating::Good, "56", second);
+        let mut session = Session::from(vec![Card::new(7, 8), Card::new(6, 9)]);
+        session.review_fact(Factors(6, 9).into(), Rating::Bad, "45", second);
+
+        let merged = merge(saved, &session).unwrap().unwrap();
+        assert_eq!(merged.history.len(), 2);
+        assert!(merge(merged, &session).unwrap().is_some());
+        session.review_fact(Factors(7, 8).into(), Rating::Good, "56", second);
+        assert!(merge(Session::from(vec![]), &session).unwrap().is_none());
+    }
+    #[test]
+    fn lock_rejects_paths_as_profile() {
+        for name in ["", "..", "../x", "a/b"] {
+            assert!(matches!(
+                Profile::open(name, Conflict::Merge),
+                Err(ErrorKind::InvalidInput(_))
+            ));
+        }
+    }
+}
@@ -1,12 +1,15 @@
 use std::process;
 
+use chrono::{Local, NaiveDate};
 use clap::Command;
 use clap::CommandFactory;
+use clap::FromArgMatches;
 use clap::Parser;
 use clap::Subcommand;
 use clap_complete::Shell;
 use clap_complete::{generate, Generator};
 use std::io;
+use std::path::PathBuf;
 
 #[derive(Parser, Debug)]
 #[clap(name = "multa")]
@@ -15,20 +18,477 @@ struct Cli {
     /// The profile to be used for the session
     #[clap(global = true, short, long, default_value = "default")]
     profile: String,
+    #[clap(flatten)]
+    practice: PracticeArgs,
     #[clap(subcommand)]
     command: Option<Commands>,
 }
 
+/// The options of the commands practicing a profile
+#[derive(clap::Args, Debug)]
+struct PracticeArgs {
+    /// Shuffle the cards from this seed, to get the same questions each run
+    #[clap(long, value_parser)]
+    seed: Option<u64>,
+    /// Introduce the tables one at a time, each once the previous ones are
+    /// mastered
+    #[clap(long)]
+    progression: bool,
+    /// Practice an imported deck instead of the times tables
+    #[clap(long)]
+    deck: Option<String>,
+    #[clap(flatten)]
+    conflict: ConflictArgs,
+}
+
+impl PracticeArgs {
+    fn with_profile(self, profile: String) -> multa::PracticeOpts {
+        multa::PracticeOpts {
+            profile,
+            seed: self.seed,
+            progression: self.progression,
+            deck: self.deck,
+            conflict: self.conflict.on_conflict.into(),
+        }
+    }
+}
+
+/// For the commands writing to a profile
+#[derive(clap::Args, Debug)]
+struct ConflictArgs {
+    /// What to do when the profile is already open in another multa:
+    /// refuse to start, practice without saving, or merge both sessions
+    #[clap(long, value_enum, default_value = "merge")]
+    on_conflict: Conflict,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+enum Conflict {
+    Refuse,
+    ReadOnly,
+    Merge,
+}
+
+impl From<Conflict> for multa::Conflict {
+    fn from(conflict: Conflict) -> Self {
+        match conflict {
+            Conflict::Refuse => multa::Conflict::Refuse,
+            Conflict::ReadOnly => multa::Conflict::ReadOnly,
+            Conflict::Merge => multa::Conflict::Merge,
+        }
+    }
+}
+
 #[derive(Subcommand, Debug)]
 enum Commands {
     /// Print last reviewed cards
-    Report,
+    Report(ReportCommand),
     /// Run multa in examination mode
-    Exam,
+    Exam(PracticeArgs),
+    /// Practice the cards you keep failing, with a hint for each
+    Leeches(PracticeArgs),
+    /// Today's challenge: the same 20 questions for everyone, in the same
+    /// order. The answers count as reviews of the profile
+    Daily(DailyCommand),
+    /// Two players or more take turns answering the same cards
+    Duel(DuelCommand),
+    /// Answer a fixed number of cards as fast as you can, against the ghost
+    /// of your best sprint of that length
+    Sprint(SprintCommand),
+    /// Answer a single card inline, e.g. from a git hook
+    Quick(PracticeArgs),
+    /// Run without a terminal UI, reading one answer per line
+    Headless(HeadlessCommand),
+    /// Drive a session from another program through a JSON-lines protocol
+    Serve(ServeCommand),
+    /// Practice in a web browser, on http://localhost
+    Web(WebCommand),
+    /// Show review statistics
+    Stats(StatsCommand),
+    /// Show how many cards will come due in the next sessions
+    Forecast(ForecastCommand),
+    /// Print the number of cards due, e.g. in a shell prompt. Exits with 1
+    /// when nothing is due, and with 2 when the profile cannot be read
+    Due(DueCommand),
+    /// Do the next assignment given by the teacher, or manage them
+    Assignment(AssignmentCommand),
+    /// Write the profile, with its history, to a single portable file
+    Export(ExportCommand),
+    /// Merge a file written by export into the profile
+    Import(ImportCommand),
+    /// Write the facts as an Anki notes file, tagged by table and with the
+    /// interval of each card in days
+    Anki(AnkiCommand),
+    /// Sync the profile with other machines through a shared directory, e.g.
+    /// on a USB stick
+    Sync(SyncCommand),
+    /// Manage a class of profiles and report on it
+    #[clap(subcommand)]
+    Class(ClassCommand),
+    /// Import decks of other facts, e.g. squares or number bonds
+    #[clap(subcommand)]
+    Deck(DeckCommand),
     /// Generate tab-completion scripts for your shell
     Completion(CompletionCommand),
 }
 
+#[derive(Parser, Debug)]
+pub struct ReportCommand {
+    /// Output format. json and csv include every card field and the review
+    /// counts and accuracy computed from the history log
+    #[clap(long, value_enum, default_value = "table")]
+    pub format: Format,
+    /// Write a self-contained HTML report to this file
+    #[clap(long, value_parser, conflicts_with = "format")]
+    pub html: Option<PathBuf>,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+pub enum Format {
+    Table,
+    Json,
+    Csv,
+}
+
+impl From<Format> for multa::Format {
+    fn from(format: Format) -> Self {
+        match format {
+            Format::Table => multa::Format::Table,
+            Format::Json => multa::Format::Json,
+            Format::Csv => multa::Format::Csv,
+        }
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct StatsCommand {
+    /// Print the statistics as JSON
+    #[clap(long)]
+    pub json: bool,
+}
+
+/// Most sessions, days or reviews per session to forecast
+const MAX_FORECAST: i64 = 1000;
+
+fn horizon() -> clap::builder::RangedU64ValueParser<usize> {
+    clap::builder::RangedU64ValueParser::new().range(0..=MAX_FORECAST as u64)
+}
+
+#[derive(Parser, Debug)]
+pub struct ForecastCommand {
+    /// Number of sessions to forecast
+    #[clap(long, value_parser = horizon(), default_value_t = 7, conflicts_with = "days")]
+    pub sessions: usize,
+    /// Forecast per day instead of per session
+    #[clap(long, value_parser = horizon())]
+    pub days: Option<usize>,
+    /// Number of reviews per session, estimated from the history by default
+    #[clap(long, value_parser = clap::value_parser!(u32).range(1..=MAX_FORECAST))]
+    pub length: Option<u32>,
+}
+
+#[derive(Parser, Debug)]
+pub struct DailyCommand {
+    /// Play the challenge of another day, e.g. 2024-01-31
+    #[clap(long, value_parser)]
+    pub date: Option<NaiveDate>,
+    #[clap(flatten)]
+    conflict: ConflictArgs,
+}
+
+#[derive(Parser, Debug)]
+pub struct DuelCommand {
+    /// The profile of each player
+    #[clap(value_parser, required = true, min_values = 2)]
+    pub players: Vec<String>,
+    /// Shuffle the cards from this seed, to get the same questions each run
+    #[clap(long, value_parser)]
+    seed: Option<u64>,
+    #[clap(flatten)]
+    conflict: ConflictArgs,
+}
+
+#[derive(Parser, Debug)]
+pub struct SprintCommand {
+    /// Number of cards to answer
+    #[clap(
+        long,
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..=1000),
+        default_value_t = 20
+    )]
+    pub length: usize,
+    #[clap(flatten)]
+    practice: PracticeArgs,
+}
+
+#[derive(Parser, Debug)]
+pub struct DueCommand {
+    /// plain prints the number, prompt a short string for a shell prompt or
+    /// a tmux status bar, empty when nothing is due
+    #[clap(long, value_enum, default_value = "plain")]
+    pub format: DueFormat,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+pub enum DueFormat {
+    Plain,
+    Json,
+    Prompt,
+}
+
+impl From<DueFormat> for multa::DueFormat {
+    fn from(format: DueFormat) -> Self {
+        match format {
+            DueFormat::Plain => multa::DueFormat::Plain,
+            DueFormat::Json => multa::DueFormat::Json,
+            DueFormat::Prompt => multa::DueFormat::Prompt,
+        }
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct ExportCommand {
+    /// Standard output by default
+    #[clap(value_parser)]
+    pub file: Option<PathBuf>,
+}
+
+#[derive(Parser, Debug)]
+pub struct AnkiCommand {
+    /// Standard output by default. Import it in Anki, then for each
+    /// multa::interval::N tag, select its cards and Set Due Date to `N!`.
+    /// A .apkg file is a package with the cards already scheduled, when
+    /// multa is built with the anki feature
+    #[clap(value_parser)]
+    pub file: Option<PathBuf>,
+    /// Export an imported deck instead of the times tables
+    #[clap(long)]
+    pub deck: Option<String>,
+}
+
+#[derive(Parser, Debug)]
+pub struct ImportCommand {
+    /// Standard input by default
+    #[clap(value_parser)]
+    pub file: Option<PathBuf>,
+    /// latest keeps the copy of each card reviewed last, replay schedules
+    /// the cards again from both histories
+    #[clap(long, value_enum, default_value = "latest")]
+    pub strategy: Strategy,
+    #[clap(flatten)]
+    conflict: ConflictArgs,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+pub enum Strategy {
+    Latest,
+    Replay,
+}
+
+impl From<Strategy> for multa::Merge {
+    fn from(strategy: Strategy) -> Self {
+        match strategy {
+            Strategy::Latest => multa::Merge::Latest,
+            Strategy::Replay => multa::Merge::Replay,
+        }
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct SyncCommand {
+    /// The shared directory. Shows the machines synced so far when omitted
+    #[clap(value_parser, conflicts_with = "off")]
+    pub dir: Option<PathBuf>,
+    /// Stop syncing the profile
+    #[clap(long)]
+    pub off: bool,
+    #[clap(flatten)]
+    conflict: ConflictArgs,
+}
+
+#[derive(Parser, Debug)]
+pub struct HeadlessCommand {
+    /// File to read answers from, stdin by default. Each line is a number,
+    /// good, bad, show, undo or exit
+    #[clap(value_parser)]
+    pub script: Option<PathBuf>,
+    /// Run in examination mode
+    #[clap(long)]
+    pub exam: bool,
+    /// Only practice leeches, with a hint for each card
+    #[clap(long)]
+    pub leeches: bool,
+    /// Do not save the results to the profile
+    #[clap(long)]
+    pub dry_run: bool,
+    #[clap(flatten)]
+    practice: PracticeArgs,
+}
+
+#[derive(Parser, Debug)]
+pub struct ServeCommand {
+    /// Read one JSON request per line on stdin and write one JSON response
+    /// per line on stdout. Requests: {"cmd": "next"}, {"cmd": "answer",
+    /// "input": "56"}, {"cmd": "rate", "rating": "good"|"bad"},
+    /// {"cmd": "undo"}, {"cmd": "summary"} and {"cmd": "save"}
+    #[clap(long, required = true)]
+    pub stdio: bool,
+    #[clap(flatten)]
+    conflict: ConflictArgs,
+}
+
+#[derive(Parser, Debug)]
+pub struct WebCommand {
+    #[clap(long, value_parser, default_value_t = 8080)]
+    pub port: u16,
+    #[clap(flatten)]
+    conflict: ConflictArgs,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ClassCommand {
+    /// Create a class with the given student profiles
+    Create {
+        class: String,
+        students: Vec<String>,
+    },
+    /// Add students to a class
+    Add {
+        class: String,
+        #[clap(required = true)]
+        students: Vec<String>,
+    },
+    /// Remove students from a class
+    Remove {
+        class: String,
+        #[clap(required = true)]
+        students: Vec<String>,
+    },
+    /// List the classes and their students
+    List,
+    /// Show the mastery, accuracy and streak of each student, and the
+    /// hardest facts of the class
+    Report {
+        class: String,
+        /// Print the report as JSON
+        #[clap(long)]
+        json: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum DeckCommand {
+    /// Import a CSV file of prompt,answer lines, e.g. `9²,81`. Answers are
+    /// whole numbers
+    Import {
+        #[clap(value_parser)]
+        file: PathBuf,
+        /// The name of the deck, the file name by default
+        #[clap(long)]
+        name: Option<String>,
+    },
+    /// List the decks
+    List,
+    /// Remove an imported deck. The progress on it is kept in the profiles
+    Remove { name: String },
+}
+
+impl From<DeckCommand> for multa::DeckCommand {
+    fn from(command: DeckCommand) -> Self {
+        match command {
+            DeckCommand::Import { file, name } => Self::Import { file, name },
+            DeckCommand::List => Self::List,
+            DeckCommand::Remove { name } => Self::Remove { name },
+        }
+    }
+}
+
+impl From<ClassCommand> for multa::ClassCommand {
+    fn from(command: ClassCommand) -> Self {
+        match command {
+            ClassCommand::Create { class, students } => Self::Create { class, students },
+            ClassCommand::Add { class, students } => Self::Add { class, students },
+            ClassCommand::Remove { class, students } => Self::Remove { class, students },
+            ClassCommand::List => Self::List,
+            ClassCommand::Report { class, json } => Self::Report { class, json },
+        }
+    }
+}
+
+#[derive(Parser, Debug)]
+#[clap(args_conflicts_with_subcommands = true)]
+pub struct AssignmentCommand {
+    /// The assignment to do, the first one due by default
+    #[clap(long, value_parser)]
+    pub name: Option<String>,
+    #[clap(flatten)]
+    conflict: ConflictArgs,
+    #[clap(subcommand)]
+    pub action: Option<AssignmentAction>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum AssignmentAction {
+    /// Give an assignment to every profile
+    Create {
+        name: String,
+        /// The tables to practice, e.g. 6,7
+        #[clap(long, value_parser, value_delimiter = ',', required = true)]
+        tables: Vec<u8>,
+        /// Number of cards to answer
+        #[clap(long, value_parser, default_value_t = 20)]
+        cards: usize,
+        /// e.g. 2024-01-31
+        #[clap(long, value_parser)]
+        due: NaiveDate,
+    },
+    /// List the assignments
+    List,
+    /// Show who in a class did an assignment and how they scored
+    Report {
+        name: String,
+        #[clap(long, value_parser)]
+        class: String,
+    },
+}
+
+impl From<Cli> for multa::AssignmentCommand {
+    fn from(cli: Cli) -> Self {
+        let AssignmentCommand {
+            name,
+            conflict,
+            action,
+        } = match cli.command {
+            Some(Commands::Assignment(command)) => command,
+            _ => AssignmentCommand {
+                name: None,
+                conflict: cli.practice.conflict,
+                action: None,
+            },
+        };
+        match action {
+            None => Self::Do {
+                profile: cli.profile,
+                name,
+                conflict: conflict.on_conflict.into(),
+            },
+            Some(AssignmentAction::Create {
+                name,
+                tables,
+                cards,
+                due,
+            }) => Self::Create(multa::Assignment {
+                name,
+                tables,
+                cards,
+                due,
+            }),
+            Some(AssignmentAction::List) => Self::List,
+            Some(AssignmentAction::Report { name, class }) => Self::Report { name, class },
+        }
+    }
+}
+
 #[derive(Parser, Debug)]
 pub struct CompletionCommand {
     #[clap(value_parser)]
@@ -37,45 +497,205 @@ pub struct CompletionCommand {
 
 impl From<Cli> for multa::Opts {
     fn from(cli: Cli) -> Self {
+        let (practice, examination, remediation, sprint) = match cli.command {
+            Some(Commands::Exam(practice)) => (practice, true, false, None),
+            Some(Commands::Leeches(practice)) => (practice, false, true, None),
+            Some(Commands::Sprint(SprintCommand { length, practice })) => {
+                (practice, false, false, Some(length))
+            }
+            _ => (cli.practice, false, false, None),
+        };
         Self {
-            examination: matches!(cli.command, Some(Commands::Exam)),
-            profile: cli.profile,
+            practice: practice.with_profile(cli.profile),
+            examination,
+            remediation,
+            sprint,
         }
     }
 }
 
 impl From<Cli> for multa::ReportOpts {
+    fn from(cli: Cli) -> Self {
+        let (format, html) = match cli.command {
+            Some(Commands::Report(ReportCommand { format, html })) => (format, html),
+            _ => (Format::Table, None),
+        };
+        Self {
+            profile: cli.profile,
+            format: format.into(),
+            html,
+        }
+    }
+}
+
+impl From<Cli> for multa::StatsOpts {
     fn from(cli: Cli) -> Self {
         Self {
+            json: matches!(
+                cli.command,
+                Some(Commands::Stats(StatsCommand { json: true }))
+            ),
             profile: cli.profile,
         }
     }
 }
 
+impl From<Cli> for multa::ForecastOpts {
+    fn from(cli: Cli) -> Self {
+        let (period, length) = match cli.command {
+            Some(Commands::Forecast(ForecastCommand {
+                days: Some(days),
+                length,
+                ..
+            })) => (multa::Period::Days(days), length),
+            Some(Commands::Forecast(ForecastCommand {
+                sessions, length, ..
+            })) => (multa::Period::Sessions(sessions), length),
+            _ => (multa::Period::Sessions(7), None),
+        };
+        Self {
+            profile: cli.profile,
+            period,
+            length,
+        }
+    }
+}
+
+impl From<Cli> for multa::HeadlessOpts {
+    fn from(cli: Cli) -> Self {
+        match cli.command {
+            Some(Commands::Headless(HeadlessCommand {
+                script,
+                exam,
+                leeches,
+                dry_run,
+                practice,
+            })) => Self {
+                practice: practice.with_profile(cli.profile),
+                script,
+                examination: exam,
+                remediation: leeches,
+                dry_run,
+            },
+            _ => Self {
+                practice: cli.practice.with_profile(cli.profile),
+                script: None,
+                examination: false,
+                remediation: false,
+                dry_run: false,
+            },
+        }
+    }
+}
+
 fn print_completions<G: Generator>(gen: G, cmd: &mut Command) {
     generate(gen, cmd, cmd.get_name().to_string(), &mut io::stdout());
 }
 
+fn fail(e: multa::ErrorKind, code: i32) -> ! {
+    println!("Application error: {:?}", e);
+
+    process::exit(code);
+}
+
 fn main() {
     env_logger::init();
-    let cli = Cli::parse();
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    if cli.command.is_some() {
+        // The practice options of the bare command, given to another one
+        let misplaced = ["seed", "progression", "deck", "on-conflict"]
+            .iter()
+            .find(|id| matches.value_source(id) == Some(clap::ValueSource::CommandLine));
+        if let Some(id) = misplaced {
+            Cli::command()
+                .error(
+                    clap::ErrorKind::ArgumentConflict,
+                    format!("--{} must follow a subcommand accepting it", id),
+                )
+                .exit();
+        }
+    }
 
-    match cli.command {
-        Some(Commands::Report) => {
-            let opts = multa::ReportOpts::from(cli);
-            multa::report(opts)
+    let result = match cli.command {
+        Some(Commands::Report(_)) => multa::report(multa::ReportOpts::from(cli)),
+        Some(Commands::Stats(_)) => multa::stats(multa::StatsOpts::from(cli)),
+        Some(Commands::Forecast(_)) => {
+            multa::forecast(multa::ForecastOpts::from(cli));
+            Ok(())
+        }
+        Some(Commands::Due(DueCommand { format })) => {
+            match multa::due(multa::DueOpts {
+                profile: cli.profile,
+                format: format.into(),
+            }) {
+                Ok(true) => Ok(()),
+                Ok(false) => process::exit(1),
+                // 1 already means nothing is due
+                Err(e) => fail(e, 2),
+            }
         }
+        Some(Commands::Duel(DuelCommand {
+            players,
+            seed,
+            conflict,
+        })) => multa::duel(multa::DuelOpts {
+            profiles: players,
+            seed,
+            conflict: conflict.on_conflict.into(),
+        }),
+        Some(Commands::Quick(practice)) => multa::quick(practice.with_profile(cli.profile)),
+        Some(Commands::Daily(DailyCommand { date, conflict })) => multa::daily(multa::DailyOpts {
+            profile: cli.profile,
+            date: date.unwrap_or_else(|| Local::now().date_naive()),
+            conflict: conflict.on_conflict.into(),
+        }),
+        Some(Commands::Headless(_)) => multa::headless(multa::HeadlessOpts::from(cli)),
+        Some(Commands::Serve(ServeCommand { conflict, .. })) => multa::serve(multa::ServeOpts {
+            profile: cli.profile,
+            conflict: conflict.on_conflict.into(),
+        }),
+        Some(Commands::Web(WebCommand { port, conflict })) => multa::web(multa::WebOpts {
+            port,
+            conflict: conflict.on_conflict.into(),
+        }),
+        Some(Commands::Assignment(_)) => multa::assignment(multa::AssignmentCommand::from(cli)),
+        Some(Commands::Export(ExportCommand { file })) => multa::export(multa::ExportOpts {
+            profile: cli.profile,
+            file,
+        }),
+        Some(Commands::Import(ImportCommand {
+            file,
+            strategy,
+            conflict,
+        })) => multa::import(multa::ImportOpts {
+            profile: cli.profile,
+            file,
+            strategy: strategy.into(),
+            conflict: conflict.on_conflict.into(),
+        }),
+        Some(Commands::Sync(SyncCommand { dir, off, conflict })) => multa::sync(multa::SyncOpts {
+            profile: cli.profile,
+            dir,
+            off,
+            conflict: conflict.on_conflict.into(),
+        }),
+        Some(Commands::Anki(AnkiCommand { file, deck })) => multa::anki(multa::AnkiOpts {
+            profile: cli.profile,
+            deck,
+            file,
+        }),
+        Some(Commands::Class(command)) => multa::class(command.into()),
+        Some(Commands::Deck(command)) => multa::deck(command.into()),
         Some(Commands::Completion(CompletionCommand { shell })) => {
             let mut cmd = Cli::command();
             print_completions(shell, &mut cmd);
+            Ok(())
         }
-        _ => {
-            let opts = multa::Opts::from(cli);
-            if let Err(e) = multa::run(opts) {
-                println!("Application error: {:?}", e);
+        _ => multa::run(multa::Opts::from(cli)),
+    };
 
-                process::exit(1);
-            }
-        }
+    if let Err(e) = result {
+        fail(e, 1);
     }
 }
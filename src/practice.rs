@@ -0,0 +1,34 @@
+use crate::deck;
+use crate::error::Result;
+use crate::lock::{Conflict, Profile};
+use crate::session::Session;
+
+/// What every way of practicing a profile has in common
+pub struct PracticeOpts {
+    pub profile: String,
+    /// Order the cards from this seed instead of at random
+    pub seed: Option<u64>,
+    /// Introduce the tables one at a time
+    pub progression: bool,
+    /// An imported deck to practice instead of the times tables
+    pub deck: Option<String>,
+    pub conflict: Conflict,
+}
+
+impl PracticeOpts {
+    /// Locks the profile and sets its session up to practice
+    pub(crate) fn open(&self) -> Result<(Profile, Session)> {
+        let profile = Profile::open(&self.profile, self.conflict)?;
+        let mut session = profile.load();
+        if let Some(name) = &self.deck {
+            session.deck(&deck::load(name)?);
+        }
+        if let Some(seed) = self.seed {
+            session.seed(seed);
+        }
+        if self.progression {
+            session.progression();
+        }
+        Ok((profile, session))
+    }
+}
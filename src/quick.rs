@@ -0,0 +1,68 @@
+use std::io::{self, BufRead, Write};
+
+use crate::engine::{Action, RatedCard, State};
+use crate::error::Result;
+use crate::practice::PracticeOpts;
+use crate::session::Session;
+
+/// Asks the next card on `out` and grades the line read from `input`. An
+/// empty answer skips the card.
+pub fn ask<R: BufRead, W: Write>(
+    session: &mut Session,
+    input: &mut R,
+    out: &mut W,
+) -> Result<Option<RatedCard>> {
+    let mut state = State::new(session, false, false);
+    let card = match state.view().card {
+        Some(card) => card,
+        None => return Ok(None),
+    };
+    write!(out, "{} = ", card)?;
+    out.flush()?;
+
+    let mut line = String::new();
+    input.read_line(&mut line)?;
+    let answer = line.trim();
+    if answer.is_empty() {
+        return Ok(None);
+    }
+
+    state.update(session, Action::Input(answer.to_string()));
+    Ok(state.view().last_card)
+}
+
+/// One card inline, without taking over the terminal
+pub fn quick(opts: PracticeOpts) -> Result<()> {
+    let (profile, mut session) = opts.open()?;
+    let stdin = io::stdin();
+    let mut out = io::stdout();
+
+    match ask(&mut session, &mut stdin.lock(), &mut out)? {
+        Some(rated) => {
+            writeln!(out, "{}", rated)?;
+            profile.save(&session)
+        }
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::card::{Card, Rating};
+
+    #[test]
+    fn quick_ask() {
+        let mut session = Session::from(vec![Card::new(7, 8)]);
+        let mut out = Vec::new();
+
+        let rated = ask(&mut session, &mut "56\n".as_bytes(), &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "7 x 8 = ");
+        assert_eq!(rated.unwrap().rating, Rating::Good);
+        assert_eq!(session.history.len(), 1);
+
+        let rated = ask(&mut session, &mut "\n".as_bytes(), &mut Vec::new()).unwrap();
+        assert!(rated.is_none());
+        assert_eq!(session.history.len(), 1);
+    }
+}
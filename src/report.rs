@@ -0,0 +1,255 @@
+use serde::Serialize;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::card::{Card, Factors, Rating, Status};
+use crate::error::Result;
+use crate::history::{self, Confusion, Stats};
+use crate::html;
+use crate::session::Session;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Table,
+    Json,
+    Csv,
+}
+
+pub struct ReportOpts {
+    pub profile: String,
+    pub format: Format,
+    /// Write a standalone HTML report to this file instead of printing
+    pub html: Option<PathBuf>,
+}
+
+/// A reviewed card as printed by `report --format json|csv`.
+///
+/// The JSON output is an array of these objects and the CSV output has one
+/// column per field, in this order, with a header row. Optional fields are
+/// `null` in JSON and empty in CSV. CSV fields are quoted when they hold a
+/// comma, a quote or a line break.
+///
+/// - `x`, `y`: the factors of the fact
+/// - `answer`: the expected product
+/// - `interval`: the current interval, in ticks
+/// - `status`: one of `unseen`, `learning` or `learned`
+/// - `due`: the tick at which the card is due, relative to the session start
+/// - `last_result`: `good` or `bad`
+/// - `last_seen`: seconds since the unix epoch
+/// - `reviews`, `good`, `bad`: review counts from the history log
+/// - `accuracy`: `good / reviews`, between 0 and 1
+/// - `lapses`: wrong answers once the card has been seen, since it was last
+///   learned
+/// - `leech`: `true` when the card keeps failing
+#[derive(Debug, PartialEq, Serialize)]
+pub struct CardReport {
+    pub x: u8,
+    pub y: u8,
+    pub answer: u8,
+    pub interval: u32,
+    pub status: &'static str,
+    pub due: Option<u32>,
+    pub last_result: Option<&'static str>,
+    pub last_seen: Option<u64>,
+    pub reviews: usize,
+    pub good: usize,
+    pub bad: usize,
+    pub accuracy: Option<f64>,
+    pub lapses: u32,
+    pub leech: bool,
+}
+
+const TOP_CONFUSIONS: usize = 5;
+
+const CSV_HEADER: &str =
+    "x,y,answer,interval,status,due,last_result,last_seen,reviews,good,bad,accuracy,lapses,leech";
+
+/// Quotes a CSV field when it holds a separator, a quote or a line break
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+fn rating_name(rating: Rating) -> &'static str {
+    match rating {
+        Rating::Good => "good",
+        Rating::Bad => "bad",
+    }
+}
+
+impl CardReport {
+    /// None for the cards of custom decks, which have no factors
+    fn new(card: &Card, stats: Stats) -> Option<CardReport> {
+        let Factors(x, y) = card.value.factors()?;
+        let (status, due) = match card.status {
+            Status::Unseen => ("unseen", None),
+            Status::Learning(due) => ("learning", Some(due)),
+            Status::Learned(due) => ("learned", Some(due)),
+        };
+
+        Some(CardReport {
+            x,
+            y,
+            answer: x * y,
+            interval: card.interval,
+            status,
+            due,
+            last_result: card.last_result.map(rating_name),
+            last_seen: card.last_seen,
+            reviews: stats.reviews(),
+            good: stats.good,
+            bad: stats.bad,
+            accuracy: stats.accuracy(),
+            lapses: card.lapses,
+            leech: card.is_leech(),
+        })
+    }
+
+    fn to_csv(&self) -> String {
+        fn opt<T: ToString>(value: Option<T>) -> String {
+            value.map(|v| v.to_string()).unwrap_or_default()
+        }
+
+        format!(
+            "{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
+            self.x,
+            self.y,
+            self.answer,
+            self.interval,
+            csv_field(self.status),
+            opt(self.due),
+            csv_field(&opt(self.last_result)),
+            opt(self.last_seen),
+            self.reviews,
+            self.good,
+            self.bad,
+            opt(self.accuracy.map(|accuracy| format!("{:.3}", accuracy))),
+            self.lapses,
+            self.leech,
+        )
+    }
+}
+
+fn print_table(cards: &[&Card], confusions: &[Confusion]) {
+    if cards.is_empty() {
+        println!("Nothing to show");
+    } else {
+        cards.iter().for_each(|card| {
+            println!(
+                "{} {} = {} interval {}",
+                if matches!(card.last_result, Some(Rating::Bad)) {
+                    "ko"
+                } else {
+                    "ok"
+                },
+                card.value,
+                card.value.answer(),
+                card.interval
+            )
+        });
+
+        let leeches: Vec<String> = cards
+            .iter()
+            .filter(|card| card.is_leech())
+            .map(|card| card.value.to_string())
+            .collect();
+        if !leeches.is_empty() {
+            println!();
+            println!("Leeches: {}", leeches.join(", "));
+        }
+
+        if !confusions.is_empty() {
+            println!();
+            println!("Confusions");
+            confusions
+                .iter()
+                .take(TOP_CONFUSIONS)
+                .for_each(|confusion| {
+                    let partners: Vec<String> = confusion
+                        .partners()
+                        .iter()
+                        .map(|partner| partner.to_string())
+                        .collect();
+                    println!(
+                        "{} = {}? ({}) {} time(s)",
+                        confusion.value,
+                        confusion.answer,
+                        partners.join(", "),
+                        confusion.count
+                    )
+                });
+        }
+    }
+}
+
+pub fn report(opts: ReportOpts) -> Result<()> {
+    let session = Session::load(&opts.profile);
+    if let Some(path) = opts.html {
+        fs::write(path, html::render(&opts.profile, &session))?;
+        return Ok(());
+    }
+
+    let mut cards: Vec<&Card> = session.cards.iter().collect();
+    cards.sort_by_key(|card| card.last_seen);
+
+    let stats = history::stats_by_fact(&session.history);
+    let rows = || {
+        cards.iter().filter_map(|card| {
+            let stats = stats.get(&card.value).copied().unwrap_or_default();
+            CardReport::new(card, stats)
+        })
+    };
+
+    match opts.format {
+        Format::Table => print_table(&cards, &history::confusions(&session.history)),
+        Format::Json => println!("{}", serde_json::to_string(&rows().collect::<Vec<_>>())?),
+        Format::Csv => {
+            println!("{}", CSV_HEADER);
+            rows().for_each(|row| println!("{}", row.to_csv()));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn card_report_csv() {
+        let card = Card {
+            value: Factors(7, 8).into(),
+            interval: 3,
+            status: Status::Learning(4),
+            last_result: Some(Rating::Good),
+            last_seen: Some(1600000000),
+            lapses: 4,
+        };
+        let row = CardReport::new(&card, Stats { good: 2, bad: 1 }).unwrap();
+
+        assert_eq!(
+            CSV_HEADER,
+            "x,y,answer,interval,status,due,last_result,last_seen,\
+             reviews,good,bad,accuracy,lapses,leech"
+        );
+        assert_eq!(
+            row.to_csv(),
+            "7,8,56,3,learning,4,good,1600000000,3,2,1,0.667,4,true"
+        );
+
+        let row = CardReport::new(&Card::new(2, 3), Stats::default()).unwrap();
+        assert_eq!(row.to_csv(), "2,3,6,55,unseen,,,,0,0,0,,0,false");
+    }
+    #[test]
+    fn csv_field_quoting() {
+        assert_eq!(csv_field("learning"), "learning");
+        assert_eq!(csv_field(""), "");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+    }
+}
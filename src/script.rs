@@ -0,0 +1,135 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::PathBuf;
+
+use crate::card::{Card, Rating};
+use crate::engine::{Action, Input, State, View};
+use crate::error::{ErrorKind, Result};
+use crate::lock::Conflict;
+use crate::practice::PracticeOpts;
+use crate::session::Session;
+
+/// Reads one action per line:
+///
+/// - a number answers the current card
+/// - `good` or `bad` rates it without typing an answer
+/// - `show` shows the answer
+/// - `undo` cancels the last review
+/// - `exit`, or the end of the input, ends the session
+///
+/// Blank lines and lines starting with `#` are ignored.
+pub struct Script<R> {
+    reader: R,
+}
+
+impl<R: BufRead> Script<R> {
+    pub fn new(reader: R) -> Script<R> {
+        Script { reader }
+    }
+}
+
+impl<R: BufRead> Input for Script<R> {
+    fn read(&mut self, _view: &View) -> Result<Action> {
+        loop {
+            let mut line = String::new();
+            if self.reader.read_line(&mut line)? == 0 {
+                return Ok(Action::Exit);
+            }
+
+            let line = line.trim();
+            return match line {
+                "" => continue,
+                _ if line.starts_with('#') => continue,
+                "good" => Ok(Action::Review(Rating::Good)),
+                "bad" => Ok(Action::Review(Rating::Bad)),
+                "show" => Ok(Action::ShowAnswer),
+                "undo" => Ok(Action::Undo),
+                "exit" => Ok(Action::Exit),
+                _ if line.chars().all(|c| c.is_ascii_digit()) => {
+                    Ok(Action::Input(line.to_string()))
+                }
+                _ => Err(ErrorKind::InvalidInput(line.to_string())),
+            };
+        }
+    }
+}
+
+/// Runs a session without a terminal UI, writing each card and result as a
+/// line of text to `out`.
+pub fn play<I: Input, W: Write>(
+    session: &mut Session,
+    state: &mut State,
+    input: &mut I,
+    out: &mut W,
+) -> Result<()> {
+    while !state.is_over() {
+        let view = state.view();
+        if let Some(hint) = view.hint {
+            writeln!(out, "{} = {}", hint, hint.compute())?;
+        }
+        if let Some(card) = &view.card {
+            if view.answer_visible {
+                writeln!(out, "{} = {}", card, card.answer())?;
+            } else {
+                writeln!(out, "{} = ?", card)?;
+            }
+        }
+
+        let action = input.read(&view)?;
+        let rated = matches!(action, Action::Input(_) | Action::Review(_));
+        state.update(session, action);
+
+        if let (true, Some(last_card)) = (rated, state.view().last_card) {
+            writeln!(out, "{}", last_card)?;
+        }
+    }
+
+    writeln!(out, "{}", state.summary())?;
+    Ok(())
+}
+
+pub struct HeadlessOpts {
+    pub practice: PracticeOpts,
+    /// Read actions from this file instead of stdin
+    pub script: Option<PathBuf>,
+    pub examination: bool,
+    pub remediation: bool,
+    /// Leave the profile untouched
+    pub dry_run: bool,
+}
+
+pub fn headless(opts: HeadlessOpts) -> Result<()> {
+    // A dry run saves nothing, the other multa needs not know about it
+    let practice = PracticeOpts {
+        conflict: if opts.dry_run {
+            Conflict::ReadOnly
+        } else {
+            opts.practice.conflict
+        },
+        ..opts.practice
+    };
+    let (profile, mut session) = practice.open()?;
+    if opts.remediation {
+        session.focus(Card::is_leech);
+    }
+    let mut state = State::new(&session, opts.examination, opts.remediation);
+    let mut out = io::stdout();
+
+    match &opts.script {
+        Some(path) => {
+            let mut input = Script::new(BufReader::new(File::open(path)?));
+            play(&mut session, &mut state, &mut input, &mut out)?
+        }
+        None => {
+            let stdin = io::stdin();
+            let mut input = Script::new(stdin.lock());
+            play(&mut session, &mut state, &mut input, &mut out)?
+        }
+    }
+
+    if opts.dry_run {
+        Ok(())
+    } else {
+        profile.save(&session)
+    }
+}
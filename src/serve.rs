@@ -0,0 +1,288 @@
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::io::{self, BufRead, Write};
+use std::time::Instant;
+
+use crate::card::{Factors, Rating};
+use crate::engine::Summary;
+use crate::error::Result;
+use crate::lock::{Conflict, Profile};
+use crate::session::Session;
+
+pub struct ServeOpts {
+    pub profile: String,
+    pub conflict: Conflict,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Grade {
+    Good,
+    Bad,
+}
+
+impl From<Grade> for Rating {
+    fn from(grade: Grade) -> Rating {
+        match grade {
+            Grade::Good => Rating::Good,
+            Grade::Bad => Rating::Bad,
+        }
+    }
+}
+
+impl From<Rating> for Grade {
+    fn from(rating: Rating) -> Grade {
+        match rating {
+            Rating::Good => Grade::Good,
+            Rating::Bad => Grade::Bad,
+        }
+    }
+}
+
+/// A fact of the times tables
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Product {
+    pub x: u8,
+    pub y: u8,
+}
+
+impl From<Factors> for Product {
+    fn from(Factors(x, y): Factors) -> Product {
+        Product { x, y }
+    }
+}
+
+/// One line of input of `multa serve --stdio`, e.g. `{"cmd": "answer",
+/// "input": "56"}`
+#[derive(Debug, PartialEq, Eq, Deserialize)]
+#[serde(tag = "cmd", rename_all = "snake_case")]
+pub enum Request {
+    /// The card to answer. Replies with `card`
+    Next,
+    /// Grades a typed answer of the current card. Replies with `rated`
+    Answer { input: String },
+    /// Rates the current card without an answer. Replies with `rated`
+    Rate { rating: Grade },
+    /// Cancels the last review. Replies with `card`
+    Undo,
+    /// Replies with `summary`
+    Summary,
+    /// Writes the profile. Replies with `saved`
+    Save,
+}
+
+/// One line of output of `multa serve --stdio`, tagged with `type`
+#[derive(Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Response {
+    /// `card` is `null` when there is nothing to practice
+    Card {
+        card: Option<Product>,
+    },
+    Rated {
+        card: Product,
+        rating: Grade,
+        expected: u8,
+    },
+    Summary {
+        good: usize,
+        bad: usize,
+    },
+    Saved,
+    Error {
+        message: String,
+    },
+}
+
+pub struct Server {
+    session: Session,
+    profile: Option<Profile>,
+    summary: Summary,
+    last_rating: Option<Rating>,
+    shown_at: Instant,
+}
+
+impl Server {
+    /// Without a profile to write to, `save` is refused
+    pub fn new(session: Session) -> Server {
+        Server {
+            session,
+            profile: None,
+            summary: Summary::new(),
+            last_rating: None,
+            shown_at: Instant::now(),
+        }
+    }
+
+    /// Practices `profile`, where `save` writes
+    pub(crate) fn open(profile: Profile) -> Server {
+        let session = profile.load();
+        Server {
+            profile: Some(profile),
+            ..Server::new(session)
+        }
+    }
+
+    /// The protocol only knows the times tables, which a session practices
+    /// unless told otherwise
+    fn current(&self) -> Option<Product> {
+        self.session
+            .peek()
+            .and_then(|card| card.value.factors())
+            .map(Product::from)
+    }
+
+    fn review(&mut self, rating: Rating, input: Option<&str>) -> Response {
+        let value = match self.session.peek().and_then(|card| card.value.factors()) {
+            Some(value) => value,
+            None => {
+                return Response::Error {
+                    message: "no card to review".to_string(),
+                }
+            }
+        };
+
+        match input {
+            Some(input) => self
+                .session
+                .review_answer(rating, input, self.shown_at.elapsed()),
+            None => self.session.review(rating),
+        }
+        match rating {
+            Rating::Good => self.summary.ok += 1,
+            Rating::Bad => self.summary.ko += 1,
+        }
+        self.last_rating = Some(rating);
+
+        Response::Rated {
+            card: value.into(),
+            rating: rating.into(),
+            expected: value.compute(),
+        }
+    }
+
+    pub fn handle(&mut self, request: Request) -> Response {
+        match request {
+            Request::Next => {
+                self.shown_at = Instant::now();
+                Response::Card {
+                    card: self.current(),
+                }
+            }
+            Request::Answer { input } => {
+                let rating = match self.session.peek() {
+                    Some(card) if input.trim() == card.value.answer() => Rating::Good,
+                    _ => Rating::Bad,
+                };
+                self.review(rating, Some(input.trim()))
+            }
+            Request::Rate { rating } => self.review(rating.into(), None),
+            Request::Undo => {
+                self.session.rollback();
+                match self.last_rating.take() {
+                    Some(Rating::Good) => self.summary.ok -= 1,
+                    Some(Rating::Bad) => self.summary.ko -= 1,
+                    None => (),
+                }
+                self.shown_at = Instant::now();
+                Response::Card {
+                    card: self.current(),
+                }
+            }
+            Request::Summary => Response::Summary {
+                good: self.summary.ok,
+                bad: self.summary.ko,
+            },
+            Request::Save => match &self.profile {
+                Some(profile) => match profile.save(&self.session) {
+                    Ok(()) => Response::Saved,
+                    Err(e) => Response::Error {
+                        message: format!("{:?}", e),
+                    },
+                },
+                None => Response::Error {
+                    message: "no profile to save to".to_string(),
+                },
+            },
+        }
+    }
+
+    /// Answers each JSON line of `reader` with a JSON line on `writer`,
+    /// until the end of the input
+    pub fn serve<R: BufRead, W: Write>(&mut self, reader: R, writer: &mut W) -> Result<()> {
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let response = match serde_json::from_str(&line) {
+                Ok(request) => self.handle(request),
+                Err(e) => Response::Error {
+                    message: e.to_string(),
+                },
+            };
+            writeln!(writer, "{}", serde_json::to_string(&response)?)?;
+            writer.flush()?;
+        }
+        Ok(())
+    }
+}
+
+pub fn serve(opts: ServeOpts) -> Result<()> {
+    // the standard output is for the frontend
+    let profile = Profile::open_quietly(&opts.profile, opts.conflict)?;
+    if let Some(notice) = profile.notice() {
+        warn!("{}", notice);
+    }
+    let mut server = Server::open(profile);
+    let stdin = io::stdin();
+    server.serve(stdin.lock(), &mut io::stdout())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::card::Card;
+
+    fn exchange(server: &mut Server, input: &str) -> Vec<String> {
+        let mut out = Vec::new();
+        server.serve(input.as_bytes(), &mut out).unwrap();
+        String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .map(String::from)
+            .collect()
+    }
+
+    #[test]
+    fn serve_protocol() {
+        let session = Session::from(vec![Card::new(7, 8)]);
+        let mut server = Server::new(session);
+
+        let lines = exchange(
+            &mut server,
+            r#"{"cmd": "next"}
+               {"cmd": "answer", "input": "54"}
+               {"cmd": "undo"}
+               {"cmd": "rate", "rating": "good"}
+               {"cmd": "summary"}
+               {"cmd": "save"}
+               {"cmd": "jump"}"#,
+        );
+
+        assert_eq!(
+            lines[..5],
+            [
+                r#"{"type":"card","card":{"x":7,"y":8}}"#,
+                r#"{"type":"rated","card":{"x":7,"y":8},"rating":"bad","expected":56}"#,
+                r#"{"type":"card","card":{"x":7,"y":8}}"#,
+                r#"{"type":"rated","card":{"x":7,"y":8},"rating":"good","expected":56}"#,
+                r#"{"type":"summary","good":1,"bad":0}"#,
+            ]
+        );
+        assert!(lines[5].starts_with(r#"{"type":"error""#));
+        assert!(lines[6].starts_with(r#"{"type":"error""#));
+        assert_eq!(server.session.history.len(), 1);
+    }
+}
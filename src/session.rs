@@ -1,17 +1,31 @@
 use serde::{Deserialize, Serialize};
 use std::cmp::{self, Ordering};
-use std::fs::File;
-use std::io::BufReader;
-use std::time::{SystemTime, UNIX_EPOCH};
-use std::{collections::HashMap, fs};
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+#[cfg(feature = "cli")]
+use std::fs;
+use std::ops::Range;
+use std::time::Duration;
 
-use crate::card::Factors;
+use crate::achievements::Badge;
 use crate::card::Status;
 use crate::card::{Card, Rating};
+use crate::card::{Fact, Factors};
+#[cfg(feature = "cli")]
+use crate::error::ErrorKind;
 use crate::error::Result;
+use crate::ghost::Sprint;
+use crate::history::{self, Completion, Review};
+#[cfg(feature = "cli")]
+use crate::sync;
+#[cfg(feature = "cli")]
+use log::warn;
 use rand::prelude::SliceRandom;
-use rand::thread_rng;
+use rand::rngs::StdRng;
+use rand::{thread_rng, Rng, SeedableRng};
+#[cfg(feature = "cli")]
 use std::path::Path;
+#[cfg(feature = "cli")]
 use std::path::PathBuf;
 
 #[derive(Debug)]
@@ -28,6 +42,15 @@ impl Intervals {
         Self::INTERVALS[Self::INTERVALS.len() - 1]
     }
 
+    /// How far `interval` is up the list, from 0 to 1
+    fn progress(interval: u32) -> f64 {
+        let max_i = Self::INTERVALS.len() - 1;
+        match Self::INTERVALS.iter().position(|&x| x == interval) {
+            Some(i) => i as f64 / max_i as f64,
+            None => 0.0,
+        }
+    }
+
     fn next(interval: u32) -> u32 {
         let max_i = Self::INTERVALS.len() - 1;
         let curr_i = Self::INTERVALS.iter().position(|&x| x == interval);
@@ -39,24 +62,41 @@ impl Intervals {
     }
 }
 
-struct TimeTables;
+pub(crate) struct TimeTables;
 impl TimeTables {
-    fn gen() -> Vec<Factors> {
+    pub(crate) fn gen<R: Rng>(rng: &mut R) -> Vec<Factors> {
         let mut items = Vec::new();
-        for x in 2..10 {
-            for y in 2..10 {
+        for x in TABLES {
+            for y in TABLES {
                 items.push(Factors(x, y));
             }
         }
-        items.shuffle(&mut thread_rng());
+        items.shuffle(rng);
         items
     }
 }
 
+/// The tables, in the order `progression` introduces them and the reports
+/// list them
+pub(crate) const TABLES: Range<u8> = 2..10;
+/// Mastery the tables need before the next one is introduced
+pub const MASTERY_THRESHOLD: f64 = 0.8;
+
+/// How `Session::merge` picks the state of each card
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Merge {
+    /// The copy of the card reviewed last
+    Latest,
+    /// Schedules the cards again from both histories
+    Replay,
+}
+
 #[derive(Debug)]
 struct Snapshot {
     cards: Vec<Card>,
+    locked: Option<Vec<Card>>,
     tick: u32,
+    history_len: usize,
 }
 
 #[derive(Debug)]
@@ -64,16 +104,85 @@ pub struct Session {
     snapshot: Option<Snapshot>,
     pub cards: Vec<Card>,
     pub tick: u32,
+    pub history: Vec<Review>,
+    /// Badges unlocked so far, see `Progress::unlock`
+    pub achievements: Vec<Badge>,
+    /// Assignments done so far
+    pub assignments: Vec<Completion>,
+    /// Sprints finished so far, raced by their `Ghost`
+    pub sprints: Vec<Sprint>,
+    /// Cards left out of the session by `focus`, saved untouched
+    aside: Vec<Card>,
+    /// Unseen cards of the tables not introduced yet, with `progression`
+    locked: Option<Vec<Card>>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 struct StoredSession {
     cards: Vec<Card>,
+    #[serde(default)]
+    history: Vec<Review>,
+    #[serde(default)]
+    achievements: Vec<Badge>,
+    #[serde(default)]
+    assignments: Vec<Completion>,
+    #[serde(default)]
+    sprints: Vec<Sprint>,
+}
+
+/// Where the profiles are stored
+#[cfg(feature = "cli")]
+pub(crate) fn data_dir() -> PathBuf {
+    let home = dirs::data_dir().expect("Cannot find data_dir");
+    Path::new(&home).join("multa")
+}
+
+/// Names of profiles and decks end up in file paths
+#[cfg(feature = "cli")]
+pub(crate) fn check_name(kind: &str, name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(ErrorKind::InvalidInput(format!(
+            "invalid {} name: {}",
+            kind, name
+        )))
+    }
+}
+
+/// The names of the `.json` files of `dir`, sorted, e.g. the classes or the
+/// decks
+#[cfg(feature = "cli")]
+pub(crate) fn json_names(dir: &Path) -> Vec<String> {
+    let mut names: Vec<String> = match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let path = entry.path();
+                if path.extension()? != "json" {
+                    return None;
+                }
+                Some(path.file_stem()?.to_str()?.to_string())
+            })
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    names.sort();
+    names
+}
+
+/// `SystemTime` is not available on wasm32-unknown-unknown, chrono is
+pub(crate) fn now() -> Option<u64> {
+    u64::try_from(chrono::Utc::now().timestamp()).ok()
 }
 
 impl Session {
     pub fn new() -> Session {
-        let cards = TimeTables::gen()
+        let cards = TimeTables::gen(&mut thread_rng())
             .iter()
             .map(|&Factors(x, y)| Card::new(x, y))
             .collect();
@@ -82,6 +191,12 @@ impl Session {
             snapshot: None,
             cards,
             tick: 0,
+            history: Vec::new(),
+            achievements: Vec::new(),
+            assignments: Vec::new(),
+            sprints: Vec::new(),
+            aside: Vec::new(),
+            locked: None,
         }
     }
 
@@ -89,6 +204,7 @@ impl Session {
         let min_due = cmp::min(
             self.cards
                 .iter()
+                .chain(&self.aside)
                 .map(|card| match card.status {
                     Status::Learning(due) | Status::Learned(due) => due,
                     _ => self.tick,
@@ -101,8 +217,9 @@ impl Session {
         let cards: Vec<Card> = self
             .cards
             .iter()
-            .cloned()
+            .chain(&self.aside)
             .filter(|card| card.status != Status::Unseen)
+            .cloned()
             .map(|card| Card {
                 status: card.status.map_due(|due| due - min_due),
                 ..card
@@ -112,35 +229,82 @@ impl Session {
         cards
     }
 
+    /// Reads a session in the format of the profile files
+    pub fn from_json(json: &str) -> Result<Session> {
+        let StoredSession {
+            cards,
+            history,
+            achievements,
+            assignments,
+            sprints,
+        } = serde_json::from_str(json)?;
+        let mut session = Session::new();
+        session.apply_changes(cards);
+        session.history = history;
+        session.achievements = achievements;
+        session.assignments = assignments;
+        session.sprints = sprints;
+        Ok(session)
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        let session = StoredSession {
+            cards: self.get_cards_to_save(),
+            history: self.history.clone(),
+            achievements: self.achievements.clone(),
+            assignments: self.assignments.clone(),
+            sprints: self.sprints.clone(),
+        };
+        Ok(serde_json::to_string(&session)?)
+    }
+
+    #[cfg(feature = "cli")]
     fn profile_path(profile: &String) -> PathBuf {
-        let home = dirs::data_dir().expect("Cannot find data_dir");
-        Path::new(&home).join("multa").join(profile)
+        data_dir().join(profile)
     }
 
+    /// With the reviews made on other machines, if the profile is synced
+    #[cfg(feature = "cli")]
     pub fn load(profile: &String) -> Session {
-        let mut session = Session::new();
+        Session::try_load(profile).unwrap()
+    }
 
-        if let Ok(file) = File::open(Session::profile_path(profile)) {
-            let reader = BufReader::new(file);
-            let StoredSession { cards } = serde_json::from_reader(reader).unwrap();
-            session.apply_changes(cards);
-        };
+    /// Like `load`, with an error instead of a panic when the profile is
+    /// corrupted
+    #[cfg(feature = "cli")]
+    pub fn try_load(profile: &String) -> Result<Session> {
+        let mut session = Session::load_local(profile)?;
+        if let Err(e) = sync::pull(&mut session, profile) {
+            warn!("Cannot sync {}: {:?}", profile, e);
+        }
+        Ok(session)
+    }
 
-        session
+    /// Like `try_load`, without reading the sync directory, which may be on
+    /// a slow drive
+    #[cfg(feature = "cli")]
+    pub fn load_local(profile: &String) -> Result<Session> {
+        match fs::read_to_string(Session::profile_path(profile)) {
+            Ok(json) => Session::from_json(&json),
+            Err(_) => Ok(Session::new()),
+        }
     }
 
-    pub fn save(self, profile: &String) -> Result<()> {
-        let cards = self.get_cards_to_save();
-        let session = StoredSession { cards };
+    #[cfg(feature = "cli")]
+    pub fn save(&self, profile: &String) -> Result<()> {
         let path = Session::profile_path(profile);
         fs::create_dir_all(path.parent().unwrap())?;
-        fs::write(path, serde_json::to_string(&session)?)?;
-        Ok(())
+        fs::write(path, self.to_json()?)?;
+        sync::push(self, profile)
     }
 
+    /// Cards of other decks than the times tables are kept aside, see
+    /// `deck`
     pub fn apply_changes(&mut self, changes: Vec<Card>) {
-        let mut card_by_value: HashMap<Factors, Card> =
-            changes.into_iter().map(|card| (card.value, card)).collect();
+        let mut card_by_value: HashMap<Fact, Card> = changes
+            .into_iter()
+            .map(|card| (card.value.clone(), card))
+            .collect();
 
         for card in self.cards.iter_mut() {
             let changed_card = card_by_value.remove(&card.value);
@@ -148,54 +312,390 @@ impl Session {
                 *card = changed_card
             }
         }
+        self.aside.extend(
+            card_by_value
+                .into_values()
+                .filter(|card| card.value.factors().is_none()),
+        );
+
+        self.rebuild();
+    }
+
+    /// Reorders the cards from `seed` alone, so that two sessions with the
+    /// same cards and the same seed ask the same questions
+    pub fn seed(&mut self, seed: u64) {
+        let mut rng = StdRng::seed_from_u64(seed);
+        self.cards.sort_by(|a, b| a.value.cmp(&b.value));
+        self.cards.shuffle(&mut rng);
+        self.rebuild();
+    }
+
+    /// How well the facts of `table` are known, from 0 to 1
+    pub fn mastery(&self, table: u8) -> f64 {
+        let locked = self.locked.iter().flatten();
+        let masteries: Vec<f64> = self
+            .cards
+            .iter()
+            .chain(&self.aside)
+            .chain(locked)
+            .filter(|card| card.value.in_table(table))
+            .map(|card| match card.status {
+                Status::Unseen => 0.0,
+                Status::Learning(_) => Intervals::progress(card.interval),
+                Status::Learned(_) => 1.0,
+            })
+            .collect();
+
+        match masteries.len() {
+            0 => 0.0,
+            n => masteries.iter().sum::<f64>() / n as f64,
+        }
+    }
 
+    /// Introduces the tables one at a time instead of all the cards at
+    /// random: the unseen cards of a table wait until the tables before it
+    /// reach `MASTERY_THRESHOLD`
+    pub fn progression(&mut self) {
+        self.locked = Some(Vec::new());
+        self.unlock_tables();
         self.rebuild();
     }
 
+    fn unlock_tables(&mut self) {
+        let open = TABLES
+            .clone()
+            .find(|&table| self.mastery(table) < MASTERY_THRESHOLD)
+            .unwrap_or(TABLES.end);
+        let locked = self.locked.take().unwrap_or_default();
+        let (locked, cards) = self.cards.drain(..).chain(locked).partition(|card| {
+            card.status == Status::Unseen
+                && card
+                    .value
+                    .factors()
+                    .is_some_and(|Factors(x, y)| cmp::min(x, y) > open)
+        });
+        self.cards = cards;
+        self.locked = Some(locked);
+    }
+
+    /// Practices `facts` instead of the times tables, which are kept aside.
+    /// The cards of the facts never practiced are made here.
+    pub fn deck(&mut self, facts: &[Fact]) {
+        let wanted: HashSet<&Fact> = facts.iter().collect();
+        let (mut cards, aside): (Vec<Card>, Vec<Card>) = self
+            .cards
+            .drain(..)
+            .chain(self.aside.drain(..))
+            .partition(|card| wanted.contains(&card.value));
+        let known: HashSet<Fact> = cards.iter().map(|card| card.value.clone()).collect();
+        let mut unseen: Vec<Card> = facts
+            .iter()
+            .filter(|fact| !known.contains(fact))
+            .map(|fact| Card::with_fact(fact.clone()))
+            .collect();
+        unseen.shuffle(&mut thread_rng());
+        cards.extend(unseen);
+
+        self.cards = cards;
+        self.aside = aside;
+        self.rebuild();
+    }
+
+    /// Restricts the session to the cards matching `predicate`
+    pub fn focus<F: Fn(&Card) -> bool>(&mut self, predicate: F) {
+        let (cards, aside) = self.cards.drain(..).partition(predicate);
+        self.cards = cards;
+        self.aside.extend(aside);
+    }
+
     pub fn peek(&self) -> Option<&Card> {
         self.cards.first()
     }
 
     pub fn review(&mut self, rating: Rating) {
+        self.record(None, rating, None, None)
+    }
+
+    /// Same as `review`, also recording the typed answer and how long it took
+    pub fn review_answer(&mut self, rating: Rating, input: &str, elapsed: Duration) {
+        self.record(None, rating, Some(input), Some(elapsed))
+    }
+
+    /// Same as `review_answer`, for `value` instead of the next card, e.g.
+    /// when the cards come from a deck shared with other players
+    pub fn review_fact(&mut self, value: Fact, rating: Rating, input: &str, elapsed: Duration) {
+        self.record(Some(value), rating, Some(input), Some(elapsed))
+    }
+
+    fn record(
+        &mut self,
+        value: Option<Fact>,
+        rating: Rating,
+        input: Option<&str>,
+        elapsed: Option<Duration>,
+    ) {
         self.snapshot = Some(Snapshot {
             cards: self.cards.clone(),
+            locked: self.locked.clone(),
             tick: self.tick,
+            history_len: self.history.len(),
         });
-        if let Some(card) = self.peek() {
-            let interval = match rating {
-                Rating::Good => Intervals::next(card.interval),
-                Rating::Bad => Intervals::first(),
-            };
+        if let Some(value) = value.or_else(|| self.peek().map(|card| card.value.clone())) {
+            self.apply(Review {
+                value,
+                rating,
+                time: now().unwrap_or_default(),
+                duration: elapsed.map(|elapsed| elapsed.as_millis() as u64),
+                input: input.map(String::from),
+            });
+        }
+    }
 
-            let value = card.value;
-            let card = self
-                .cards
-                .iter_mut()
-                .find(|card| card.value == value)
-                .unwrap();
-
-            let due = self.tick + interval;
-            card.interval = interval;
-            card.last_result = Some(rating);
-            card.last_seen = SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .ok()
-                .map(|duration| duration.as_secs());
-            card.status = if interval == Intervals::last() {
-                Status::Learned(due)
-            } else {
-                Status::Learning(due)
+    /// Schedules the card of `review` and adds it to the history. The cards
+    /// of custom decks are kept aside, and created on their first review.
+    fn apply(&mut self, review: Review) {
+        let found = self
+            .cards
+            .iter()
+            .chain(&self.aside)
+            .any(|card| card.value == review.value);
+        if !found {
+            match review.value {
+                Fact::Custom(_) => self.aside.push(Card::with_fact(review.value.clone())),
+                Fact::Product(_) => return,
+            }
+        }
+        let card = match self
+            .cards
+            .iter_mut()
+            .chain(self.aside.iter_mut())
+            .find(|card| card.value == review.value)
+        {
+            Some(card) => card,
+            None => return,
+        };
+        let interval = match review.rating {
+            Rating::Good => Intervals::next(card.interval),
+            Rating::Bad => Intervals::first(),
+        };
+
+        let due = self.tick + interval;
+        if review.rating == Rating::Bad && card.status != Status::Unseen {
+            card.lapses += 1;
+        }
+        card.interval = interval;
+        card.last_result = Some(review.rating);
+        card.last_seen = Some(review.time);
+        card.status = if interval == Intervals::last() {
+            // mastered again, it is no longer a leech
+            card.lapses = 0;
+            Status::Learned(due)
+        } else {
+            Status::Learning(due)
+        };
+
+        let (value, rating) = (review.value.factors(), review.rating);
+        self.history.push(review);
+        self.tick += 1;
+        if let (Some(value), Rating::Bad) = (value, rating) {
+            self.interleave_confusions(value);
+        }
+        if self.locked.is_some() {
+            self.unlock_tables();
+        }
+        self.rebuild();
+    }
+
+    /// The session that `history` leads to from a fresh deck
+    pub fn replay(history: Vec<Review>) -> Session {
+        Session::replay_onto(Vec::new(), history)
+    }
+
+    /// Same as `replay`, from `cards` instead of unseen cards
+    fn replay_onto(cards: Vec<Card>, history: Vec<Review>) -> Session {
+        let mut session = Session::new();
+        session.apply_changes(cards);
+        for review in history {
+            session.apply(review);
+        }
+        session
+    }
+
+    /// The cards whose progress `history` does not account for: facts it
+    /// never reviews, or progress made before their first review in it, e.g.
+    /// in the profiles saved before the history was recorded. They are due
+    /// as from the end of `history`.
+    fn unrecorded(&self, history: &[Review]) -> Vec<Card> {
+        let mut first_reviews: HashMap<&Fact, u64> = HashMap::new();
+        for review in history {
+            first_reviews.entry(&review.value).or_insert(review.time);
+        }
+        let end = history.len() as u32;
+        self.cards
+            .iter()
+            .chain(&self.aside)
+            .filter(|card| card.status != Status::Unseen)
+            .filter(|card| match first_reviews.get(&card.value) {
+                Some(&first) => card.last_seen.is_none_or(|seen| seen < first),
+                None => true,
+            })
+            .cloned()
+            .map(|card| Card {
+                status: card
+                    .status
+                    .map_due(|due| due.saturating_sub(self.tick) + end),
+                ..card
+            })
+            .collect()
+    }
+
+    /// Reconciles two copies of the same profile
+    pub fn merge(&mut self, other: Session, strategy: Merge) {
+        let history = history::merge(&self.history, &other.history);
+        match strategy {
+            Merge::Latest => {
+                let mut others: HashMap<Fact, Card> = other
+                    .cards
+                    .into_iter()
+                    .chain(other.aside)
+                    .map(|card| (card.value.clone(), card))
+                    .collect();
+                let locked = self.locked.iter_mut().flatten();
+                for card in self
+                    .cards
+                    .iter_mut()
+                    .chain(self.aside.iter_mut())
+                    .chain(locked)
+                {
+                    match others.remove(&card.value) {
+                        Some(other) if other.last_seen > card.last_seen => *card = other,
+                        _ => (),
+                    }
+                }
+                // facts of decks only practiced in `other`
+                self.aside.extend(others.into_values());
+                self.history = history;
+                if self.locked.is_some() {
+                    self.unlock_tables();
+                }
+            }
+            Merge::Replay => {
+                let mut base: HashMap<Fact, Card> = HashMap::new();
+                for card in self
+                    .unrecorded(&history)
+                    .into_iter()
+                    .chain(other.unrecorded(&history))
+                {
+                    match base.get(&card.value) {
+                        Some(known) if known.last_seen >= card.last_seen => (),
+                        _ => {
+                            base.insert(card.value.clone(), card);
+                        }
+                    }
+                }
+
+                let replayed = Session::replay_onto(base.into_values().collect(), history);
+                self.cards = replayed.cards;
+                self.aside = replayed.aside;
+                self.tick = replayed.tick;
+                self.history = replayed.history;
+                if self.locked.is_some() {
+                    // the replayed cards include the locked ones
+                    self.locked = Some(Vec::new());
+                    self.unlock_tables();
+                }
+            }
+        }
+
+        for badge in other.achievements {
+            if !self.achievements.contains(&badge) {
+                self.achievements.push(badge);
+            }
+        }
+        for completion in other.assignments {
+            if !self.assignments.contains(&completion) {
+                self.assignments.push(completion);
+            }
+        }
+        for sprint in other.sprints {
+            if !self.sprints.contains(&sprint) {
+                self.sprints.push(sprint);
+            }
+        }
+        self.snapshot = None;
+        self.rebuild();
+    }
+
+    /// Brings the facts `value` gets confused with forward, so that both are
+    /// practiced together. Learned facts stay learned, only due sooner.
+    fn interleave_confusions(&mut self, value: Factors) {
+        let partners: HashSet<Fact> = history::confusions(&self.history)
+            .into_iter()
+            .filter(|confusion| confusion.value == value)
+            .flat_map(|confusion| confusion.partners())
+            .map(Fact::from)
+            .collect();
+
+        let tick = self.tick;
+        for card in self.cards.iter_mut() {
+            if partners.contains(&card.value) {
+                card.status = match card.status {
+                    Status::Unseen => Status::Unseen,
+                    Status::Learning(due) => Status::Learning(due.min(tick)),
+                    Status::Learned(due) => Status::Learned(due.min(tick)),
+                };
+            }
+        }
+    }
+
+    /// Number of seen cards coming due within the next `length` reviews
+    pub fn due(&self, length: u32) -> usize {
+        let horizon = self.tick + length;
+        self.cards
+            .iter()
+            .filter(|card| match card.status {
+                Status::Unseen => false,
+                Status::Learning(due) | Status::Learned(due) => due < horizon,
+            })
+            .count()
+    }
+
+    /// Number of cards coming due in each of the next `sessions` sessions of
+    /// `length` reviews, assuming every review is good
+    pub fn forecast(&self, length: u32, sessions: usize) -> Vec<usize> {
+        let sessions_length = u32::try_from(sessions)
+            .unwrap_or(u32::MAX)
+            .saturating_mul(length);
+        let horizon = self.tick.saturating_add(sessions_length);
+        let mut counts = vec![0; sessions];
+
+        for card in &self.cards {
+            let (mut due, mut interval) = match card.status {
+                Status::Unseen => continue,
+                Status::Learning(due) | Status::Learned(due) => {
+                    (cmp::max(due, self.tick), card.interval)
+                }
             };
 
-            self.tick += 1;
-            self.rebuild();
+            while due < horizon {
+                counts[((due - self.tick) / length) as usize] += 1;
+                interval = Intervals::next(interval);
+                due = due.saturating_add(interval);
+                if due == u32::MAX {
+                    break;
+                }
+            }
         }
+
+        counts
     }
 
     pub fn rollback(&mut self) {
         if let Some(snapshot) = &self.snapshot {
             self.cards = snapshot.cards.clone();
+            self.locked = snapshot.locked.clone();
             self.tick = snapshot.tick;
+            self.history.truncate(snapshot.history_len);
             self.snapshot = None;
         }
     }
@@ -218,12 +718,24 @@ impl Session {
     }
 }
 
+impl Default for Session {
+    fn default() -> Session {
+        Session::new()
+    }
+}
+
 impl From<Vec<Card>> for Session {
     fn from(cards: Vec<Card>) -> Session {
         let mut session = Session {
             snapshot: None,
             cards,
             tick: 0,
+            history: Vec::new(),
+            achievements: Vec::new(),
+            assignments: Vec::new(),
+            sprints: Vec::new(),
+            aside: Vec::new(),
+            locked: None,
         };
         session.rebuild();
         session
@@ -233,14 +745,20 @@ impl From<Vec<Card>> for Session {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::card::{Custom, LEECH_THRESHOLD};
+
+    fn fact(x: u8, y: u8) -> Fact {
+        Factors(x, y).into()
+    }
 
     fn a_card(id: u8, status: Status) -> Card {
         Card {
             status,
             interval: 2,
-            value: Factors(id, id),
+            value: fact(id, id),
             last_result: None,
             last_seen: None,
+            lapses: 0,
         }
     }
 
@@ -294,6 +812,12 @@ mod tests {
         let session = Session {
             snapshot: None,
             tick: 2,
+            history: Vec::new(),
+            achievements: Vec::new(),
+            assignments: Vec::new(),
+            sprints: Vec::new(),
+            aside: Vec::new(),
+            locked: None,
             cards: vec![
                 a_card(1, Status::Learning(3)),
                 a_card(2, Status::Learning(4)),
@@ -311,6 +835,12 @@ mod tests {
         let session = Session {
             snapshot: None,
             tick: 6,
+            history: Vec::new(),
+            achievements: Vec::new(),
+            assignments: Vec::new(),
+            sprints: Vec::new(),
+            aside: Vec::new(),
+            locked: None,
             cards: vec![a_card(1, Status::Learning(5))],
         };
 
@@ -331,49 +861,49 @@ mod tests {
 
         assert_eq!(session.tick, 0);
         let card = session.peek().unwrap();
-        assert_eq!(card.value, Factors(9, 9));
+        assert_eq!(card.value, fact(9, 9));
         session.review(Rating::Bad);
         // 9x9 due: 2,  interval: 2
 
         assert_eq!(session.tick, 1);
         let card = session.peek().unwrap();
-        assert_eq!(card.value, Factors(8, 8));
+        assert_eq!(card.value, fact(8, 8));
         session.review(Rating::Good);
         // 8x8 due: 4,  interval: 3
 
         assert_eq!(session.tick, 2);
         let card = session.peek().unwrap();
-        assert_eq!(card.value, Factors(9, 9));
+        assert_eq!(card.value, fact(9, 9));
         session.review(Rating::Good);
         // 9x9 due: 5,  interval: 3
 
         assert_eq!(session.tick, 3);
         let card = session.peek().unwrap();
-        assert_eq!(card.value, Factors(7, 7));
+        assert_eq!(card.value, fact(7, 7));
         session.review(Rating::Good);
         // 7x7 due: 6,  interval: 3
 
         assert_eq!(session.tick, 4);
         let card = session.peek().unwrap();
-        assert_eq!(card.value, Factors(8, 8));
+        assert_eq!(card.value, fact(8, 8));
         session.review(Rating::Good);
         // 8x8 due: 9,  interval: 5
 
         assert_eq!(session.tick, 5);
         let card = session.peek().unwrap();
-        assert_eq!(card.value, Factors(9, 9));
+        assert_eq!(card.value, fact(9, 9));
         session.review(Rating::Good);
         // 9x9 due: 10,  interval: 5
 
         assert_eq!(session.tick, 6);
         let card = session.peek().unwrap();
-        assert_eq!(card.value, Factors(7, 7));
+        assert_eq!(card.value, fact(7, 7));
         session.review(Rating::Good);
         // 7x7 due: 11,  interval: 5
 
         assert_eq!(session.tick, 7);
         let card = session.peek().unwrap();
-        assert_eq!(card.value, Factors(6, 6));
+        assert_eq!(card.value, fact(6, 6));
         session.review(Rating::Good);
         // 6x6 due: 10,  interval: 3
     }
@@ -389,15 +919,382 @@ mod tests {
 
         assert_eq!(session.tick, 0);
         let card = session.peek().unwrap();
-        assert_eq!(card.value, Factors(9, 9));
+        assert_eq!(card.value, fact(9, 9));
         session.review(Rating::Bad);
         // 9x9 due: 3,  interval: 3
 
         assert_eq!(session.tick, 1);
         let card = session.peek().unwrap();
-        assert_eq!(card.value, Factors(8, 8));
+        assert_eq!(card.value, fact(8, 8));
         session.rollback();
         let card = session.peek().unwrap();
-        assert_eq!(card.value, Factors(9, 9));
+        assert_eq!(card.value, fact(9, 9));
+    }
+
+    #[test]
+    fn session_review_history() {
+        let mut session = Session::from(vec![a_card(9, Status::Unseen), a_card(8, Status::Unseen)]);
+
+        session.review(Rating::Bad);
+        session.review(Rating::Good);
+        assert_eq!(
+            session
+                .history
+                .iter()
+                .map(|review| (review.value.clone(), review.rating))
+                .collect::<Vec<_>>(),
+            vec![(fact(9, 9), Rating::Bad), (fact(8, 8), Rating::Good)]
+        );
+
+        session.rollback();
+        assert_eq!(session.history.len(), 1);
+        assert_eq!(session.history[0].value, fact(9, 9));
+    }
+
+    #[test]
+    fn session_json_roundtrip() {
+        let mut session = Session::new();
+        session.review(Rating::Good);
+        session.review(Rating::Bad);
+
+        let restored = Session::from_json(&session.to_json().unwrap()).unwrap();
+        assert_eq!(restored.history, session.history);
+        assert_eq!(restored.get_cards_to_save(), session.get_cards_to_save());
+        assert!(Session::from_json("{").is_err());
+    }
+
+    #[test]
+    fn session_review_lapses() {
+        let mut session = Session::from(vec![a_card(9, Status::Unseen)]);
+
+        session.review(Rating::Bad);
+        assert_eq!(session.cards[0].lapses, 0);
+        session.review(Rating::Good);
+        session.review(Rating::Bad);
+        session.review(Rating::Bad);
+        assert_eq!(session.cards[0].lapses, 2);
+        session.rollback();
+        assert_eq!(session.cards[0].lapses, 1);
+    }
+
+    #[test]
+    fn session_learned_leech_is_forgiven() {
+        let mut session = Session::from(vec![Card {
+            lapses: LEECH_THRESHOLD,
+            ..a_card(9, Status::Learning(0))
+        }]);
+        assert!(session.cards[0].is_leech());
+
+        while !matches!(session.cards[0].status, Status::Learned(_)) {
+            assert!(session.cards[0].is_leech());
+            session.review(Rating::Good);
+        }
+        assert_eq!(session.cards[0].lapses, 0);
+        assert!(!session.cards[0].is_leech());
+    }
+
+    #[test]
+    fn session_focus() {
+        let mut session = Session::from(vec![
+            a_card(1, Status::Learning(0)),
+            a_card(2, Status::Learning(1)),
+            a_card(3, Status::Unseen),
+        ]);
+        session.focus(|card| card.value == fact(2, 2));
+
+        assert_eq!(session.cards, vec![a_card(2, Status::Learning(1))]);
+        assert_eq!(
+            session.get_cards_to_save(),
+            vec![
+                a_card(2, Status::Learning(1)),
+                a_card(1, Status::Learning(0))
+            ]
+        );
+    }
+
+    #[test]
+    fn session_review_interleaves_confusions() {
+        let mut session = Session::from(vec![
+            Card {
+                value: fact(7, 8),
+                ..a_card(0, Status::Learning(0))
+            },
+            Card {
+                value: fact(6, 9),
+                ..a_card(0, Status::Learning(10))
+            },
+            a_card(2, Status::Learning(3)),
+        ]);
+
+        session.review_answer(Rating::Bad, "54", Duration::from_secs(1));
+        assert_eq!(session.history[0].input.as_deref(), Some("54"));
+        assert_eq!(session.peek().unwrap().value, fact(6, 9));
+    }
+
+    #[test]
+    fn session_review_keeps_confused_learned_facts_learned() {
+        let mut session = Session::from(vec![
+            Card {
+                value: fact(7, 8),
+                ..a_card(0, Status::Learning(0))
+            },
+            Card {
+                value: fact(6, 9),
+                ..a_card(0, Status::Learned(40))
+            },
+        ]);
+
+        session.review_answer(Rating::Bad, "54", Duration::from_secs(1));
+        let partner = session
+            .cards
+            .iter()
+            .find(|card| card.value == fact(6, 9))
+            .unwrap();
+        assert_eq!(partner.status, Status::Learned(1));
+    }
+
+    #[test]
+    fn session_forecast() {
+        let session = Session {
+            snapshot: None,
+            tick: 4,
+            history: Vec::new(),
+            achievements: Vec::new(),
+            assignments: Vec::new(),
+            sprints: Vec::new(),
+            aside: Vec::new(),
+            locked: None,
+            cards: vec![
+                a_card(1, Status::Learning(2)),
+                a_card(2, Status::Learning(6)),
+                a_card(3, Status::Learned(40)),
+                a_card(4, Status::Unseen),
+            ],
+        };
+
+        // 1x1 due: 4, 7, 12; 2x2 due: 6, 9
+        assert_eq!(session.forecast(5, 2), vec![3, 2]);
+        assert_eq!(session.forecast(5, 0), Vec::<usize>::new());
+
+        // the horizon would be past the last tick
+        let session = Session {
+            tick: u32::MAX - 10,
+            cards: vec![a_card(1, Status::Learning(u32::MAX - 8))],
+            ..session
+        };
+        assert_eq!(session.forecast(u32::MAX, 2), vec![2, 0]);
+    }
+
+    #[test]
+    fn session_due() {
+        let session = Session {
+            snapshot: None,
+            tick: 4,
+            history: Vec::new(),
+            achievements: Vec::new(),
+            assignments: Vec::new(),
+            sprints: Vec::new(),
+            aside: Vec::new(),
+            locked: None,
+            cards: vec![
+                a_card(1, Status::Learning(2)),
+                a_card(2, Status::Learning(6)),
+                a_card(3, Status::Learned(40)),
+                a_card(4, Status::Unseen),
+            ],
+        };
+
+        assert_eq!(session.due(1), 1);
+        assert_eq!(session.due(5), 2);
+        assert_eq!(session.due(100), 3);
+    }
+
+    #[test]
+    fn session_seed() {
+        let values = |session: &Session| -> Vec<Fact> {
+            session
+                .cards
+                .iter()
+                .map(|card| card.value.clone())
+                .collect()
+        };
+        let (mut a, mut b) = (Session::new(), Session::new());
+        a.seed(42);
+        b.seed(42);
+        assert_eq!(values(&a), values(&b));
+
+        b.seed(43);
+        assert_ne!(values(&a), values(&b));
+    }
+
+    #[test]
+    fn session_replay() {
+        let mut session = Session::from(vec![Card::new(7, 8), Card::new(6, 9)]);
+        session.review(Rating::Good);
+        session.review(Rating::Bad);
+
+        let replayed = Session::replay(session.history.clone());
+        assert_eq!(replayed.history, session.history);
+        assert_eq!(replayed.tick, 2);
+        for card in session.cards {
+            let replayed = replayed.cards.iter().find(|c| c.value == card.value);
+            assert_eq!(replayed, Some(&card));
+        }
+    }
+
+    #[test]
+    fn session_mastery() {
+        let mut learning = a_card(3, Status::Learning(0));
+        learning.value = fact(2, 3);
+        learning.interval = 8;
+        let mut learned = a_card(2, Status::Learned(0));
+        learned.interval = 55;
+        let session = Session::from(vec![learned, learning, a_card(4, Status::Unseen)]);
+
+        assert_eq!(session.mastery(2), (1.0 + 3.0 / 7.0) / 2.0);
+        assert_eq!(session.mastery(4), 0.0);
+        assert_eq!(session.mastery(5), 0.0);
+    }
+
+    #[test]
+    fn session_progression() {
+        let mut session = Session::new();
+        session.progression();
+        let tables = |session: &Session| -> HashSet<u8> {
+            session
+                .cards
+                .iter()
+                .filter_map(|card| card.value.factors())
+                .map(|Factors(x, y)| cmp::min(x, y))
+                .collect()
+        };
+        assert_eq!(tables(&session), [2].iter().copied().collect());
+
+        while session.mastery(2) < MASTERY_THRESHOLD {
+            session.review(Rating::Good);
+        }
+        assert!(tables(&session).contains(&3));
+        assert!(!tables(&session).contains(&4));
+
+        session.rollback();
+        assert_eq!(
+            session.cards.len() + session.locked.iter().flatten().count(),
+            64
+        );
+        assert!(!tables(&session).contains(&3));
+    }
+
+    #[test]
+    fn session_merge() {
+        let mut school = Session::from(vec![Card::new(7, 8), Card::new(6, 9)]);
+        let mut home = Session::from(vec![Card::new(7, 8), Card::new(6, 9)]);
+        school.review(Rating::Bad);
+        home.review_fact(fact(6, 9), Rating::Good, "54", Duration::from_secs(1));
+        for card in home.cards.iter_mut() {
+            card.last_seen = card.last_seen.map(|time| time + 1);
+        }
+        home.history[0].time += 1;
+
+        let mut latest = Session::from(school.cards.clone());
+        latest.history = school.history.clone();
+        latest.merge(Session::from(home.cards.clone()), Merge::Latest);
+        let status = |session: &Session, value| {
+            let card = session.cards.iter().find(|card| card.value == value);
+            card.map(|card| card.status.clone())
+        };
+        assert!(matches!(
+            status(&latest, fact(7, 8)),
+            Some(Status::Learning(_))
+        ));
+        assert!(matches!(
+            status(&latest, fact(6, 9)),
+            Some(Status::Learned(_))
+        ));
+
+        let mut replayed = Session::from(vec![]);
+        replayed.history = school.history.clone();
+        let mut other = Session::from(vec![]);
+        other.history = home.history.clone();
+        other.history.extend(school.history.clone());
+        replayed.merge(other, Merge::Replay);
+        assert_eq!(replayed.history.len(), 2);
+        assert_eq!(replayed.history[0].value, fact(7, 8));
+        assert!(matches!(
+            status(&replayed, fact(7, 8)),
+            Some(Status::Learning(_))
+        ));
+        assert!(matches!(
+            status(&replayed, fact(6, 9)),
+            Some(Status::Learned(_))
+        ));
+        assert_eq!(replayed.cards.len(), 64);
+    }
+
+    #[test]
+    fn session_deck_roundtrip() {
+        let square = |n: u64| {
+            Fact::Custom(Custom {
+                deck: "squares".to_string(),
+                prompt: format!("{}²", n),
+                answer: (n * n).to_string(),
+            })
+        };
+        let mut session = Session::new();
+        session.deck(&[square(9), square(8)]);
+        assert_eq!(session.cards.len(), 2);
+        assert_eq!(session.aside.len(), 64);
+
+        session.review(Rating::Good);
+        let reviewed = session.history[0].value.clone();
+        let json = session.to_json().unwrap();
+        let saved = Session::from_json(&json).unwrap();
+        assert_eq!(saved.cards.len(), 64);
+        assert_eq!(saved.aside.len(), 1);
+
+        let custom = |session: &Session| {
+            let card = session.aside.iter().find(|card| card.value == reviewed);
+            card.map(|card| card.status.clone())
+        };
+        for strategy in [Merge::Latest, Merge::Replay] {
+            let mut merged = Session::new();
+            merged.merge(Session::from_json(&json).unwrap(), strategy);
+            assert!(matches!(custom(&merged), Some(Status::Learned(_))));
+
+            let mut merged = Session::from_json(&json).unwrap();
+            merged.merge(Session::new(), strategy);
+            assert!(matches!(custom(&merged), Some(Status::Learned(_))));
+        }
+    }
+
+    #[test]
+    fn session_merge_keeps_progress_missing_from_the_history() {
+        let learned = |value| Card {
+            value,
+            interval: 55,
+            last_seen: Some(10),
+            ..a_card(0, Status::Learned(10))
+        };
+        // saved before the history was recorded
+        let mut legacy = Session::new();
+        legacy.apply_changes(vec![learned(fact(7, 8)), learned(fact(6, 9))]);
+        let mut other = Session::from(vec![]);
+        other.history = vec![Review {
+            value: fact(6, 9),
+            rating: Rating::Good,
+            time: 100,
+            duration: None,
+            input: None,
+        }];
+
+        legacy.progression();
+        legacy.merge(other, Merge::Replay);
+        let card = |value| legacy.cards.iter().find(|card| card.value == value);
+        assert_eq!(card(fact(7, 8)).unwrap().status, Status::Learned(11));
+        assert!(matches!(
+            card(fact(6, 9)).unwrap().status,
+            Status::Learned(_)
+        ));
+        let locked = legacy.locked.as_ref().unwrap();
+        assert_eq!(legacy.cards.len() + locked.len(), 64);
     }
 }
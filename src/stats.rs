@@ -0,0 +1,278 @@
+use chrono::{Local, NaiveDate};
+use serde::Serialize;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::card::{Card, Factors, Status};
+use crate::error::Result;
+use crate::history::{self, Review, Stats};
+use crate::session::{Session, TABLES};
+
+const CHART_WIDTH: usize = 30;
+const CHART_DAYS: usize = 14;
+const TOP_LAPSES: usize = 5;
+
+pub struct StatsOpts {
+    pub profile: String,
+    pub json: bool,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Day {
+    pub date: NaiveDate,
+    pub reviews: usize,
+    pub good: usize,
+    pub bad: usize,
+    pub accuracy: Option<f64>,
+    /// Average time taken to answer, in milliseconds
+    pub average_duration: Option<u64>,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct Buckets {
+    pub unseen: usize,
+    pub learning: usize,
+    pub learned: usize,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Lapses {
+    pub x: u8,
+    pub y: u8,
+    pub lapses: usize,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Mastery {
+    pub table: u8,
+    /// From 0 to 1, see `Session::mastery`
+    pub mastery: f64,
+}
+
+/// Everything shown by `multa stats`, also its JSON output
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Dashboard {
+    pub days: Vec<Day>,
+    pub status: Buckets,
+    /// Consecutive days of practice
+    pub streak: usize,
+    pub lapses: Vec<Lapses>,
+    pub mastery: Vec<Mastery>,
+}
+
+fn days(history: &[Review]) -> Vec<Day> {
+    let mut by_date: BTreeMap<NaiveDate, Vec<&Review>> = BTreeMap::new();
+    for review in history {
+        by_date.entry(review.date()).or_default().push(review);
+    }
+
+    by_date
+        .into_iter()
+        .map(|(date, reviews)| {
+            let mut stats = Stats::default();
+            reviews.iter().for_each(|review| stats.add(review.rating));
+
+            let durations: Vec<u64> = reviews.iter().filter_map(|r| r.duration).collect();
+            let average_duration = match durations.len() {
+                0 => None,
+                n => Some(durations.iter().sum::<u64>() / n as u64),
+            };
+
+            Day {
+                date,
+                reviews: stats.reviews(),
+                good: stats.good,
+                bad: stats.bad,
+                accuracy: stats.accuracy(),
+                average_duration,
+            }
+        })
+        .collect()
+}
+
+fn buckets(cards: &[Card]) -> Buckets {
+    let mut buckets = Buckets::default();
+    for card in cards {
+        match card.status {
+            Status::Unseen => buckets.unseen += 1,
+            Status::Learning(_) => buckets.learning += 1,
+            Status::Learned(_) => buckets.learned += 1,
+        }
+    }
+    buckets
+}
+
+impl Dashboard {
+    pub fn new(session: &Session, today: NaiveDate) -> Dashboard {
+        let dates: BTreeSet<NaiveDate> = session.history.iter().map(Review::date).collect();
+
+        let mut lapses: Vec<Lapses> = history::lapses_by_fact(&session.history)
+            .into_iter()
+            .filter_map(|(value, lapses)| {
+                let Factors(x, y) = value.factors()?;
+                Some(Lapses { x, y, lapses })
+            })
+            .collect();
+        lapses.sort_by_key(|l| (Reverse(l.lapses), l.x, l.y));
+        lapses.truncate(TOP_LAPSES);
+
+        Dashboard {
+            days: days(&session.history),
+            status: buckets(&session.cards),
+            streak: history::streak(&dates, today),
+            lapses,
+            mastery: TABLES
+                .map(|table| Mastery {
+                    table,
+                    mastery: session.mastery(table),
+                })
+                .collect(),
+        }
+    }
+}
+
+fn bar(value: f64, max: f64) -> String {
+    let width = if max > 0.0 {
+        (value / max * CHART_WIDTH as f64).round() as usize
+    } else {
+        0
+    };
+    "█".repeat(width)
+}
+
+fn chart<F: Fn(&Day) -> Option<f64>>(
+    title: &str,
+    days: &[Day],
+    value: F,
+    precision: usize,
+    unit: &str,
+) {
+    println!("{}", title);
+    let max = days.iter().filter_map(&value).fold(0.0, f64::max);
+    for day in days {
+        match value(day) {
+            Some(v) => println!("{} {} {:.*}{}", day.date, bar(v, max), precision, v, unit),
+            None => println!("{} -", day.date),
+        }
+    }
+    println!();
+}
+
+/// "s" unless there is exactly one
+fn plural(x: usize) -> &'static str {
+    if x != 1 {
+        "s"
+    } else {
+        ""
+    }
+}
+
+fn print_dashboard(dashboard: &Dashboard) {
+    let recent = &dashboard.days[dashboard.days.len().saturating_sub(CHART_DAYS)..];
+
+    chart("Reviews per day", recent, |d| Some(d.reviews as f64), 0, "");
+    chart(
+        "Accuracy",
+        recent,
+        |d| d.accuracy.map(|a| a * 100.0),
+        0,
+        "%",
+    );
+    chart(
+        "Average response time",
+        recent,
+        |d| d.average_duration.map(|ms| ms as f64 / 1000.0),
+        1,
+        "s",
+    );
+
+    println!("Mastery");
+    for Mastery { table, mastery } in &dashboard.mastery {
+        println!("{}s {} {:.0}%", table, bar(*mastery, 1.0), mastery * 100.0);
+    }
+    println!();
+
+    let Buckets {
+        unseen,
+        learning,
+        learned,
+    } = dashboard.status;
+    println!(
+        "Cards: {} unseen; {} learning; {} learned",
+        unseen, learning, learned
+    );
+    println!(
+        "Streak: {} day{}",
+        dashboard.streak,
+        plural(dashboard.streak)
+    );
+
+    if !dashboard.lapses.is_empty() {
+        println!();
+        println!("Most lapses");
+        for Lapses { x, y, lapses } in &dashboard.lapses {
+            println!(
+                "{} x {} = {}  {} lapse{}",
+                x,
+                y,
+                x * y,
+                lapses,
+                plural(*lapses)
+            );
+        }
+    }
+}
+
+pub fn stats(opts: StatsOpts) -> Result<()> {
+    let session = Session::load(&opts.profile);
+    let dashboard = Dashboard::new(&session, Local::now().date_naive());
+
+    if opts.json {
+        println!("{}", serde_json::to_string(&dashboard)?);
+    } else {
+        print_dashboard(&dashboard);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::card::{Factors, Rating};
+
+    fn a_review(rating: Rating, time: u64, duration: Option<u64>) -> Review {
+        Review {
+            value: Factors(7, 8).into(),
+            rating,
+            time,
+            duration,
+            input: None,
+        }
+    }
+
+    #[test]
+    fn days_per_date() {
+        let noon = 1_600_000_000 - 1_600_000_000 % 86400 + 43200;
+        let days = days(&[
+            a_review(Rating::Good, noon, Some(1000)),
+            a_review(Rating::Bad, noon + 60, Some(3000)),
+            a_review(Rating::Good, noon + 60, None),
+            a_review(Rating::Good, noon + 86400, None),
+        ]);
+
+        assert_eq!(days.len(), 2);
+        assert_eq!(days[0].reviews, 3);
+        assert_eq!(days[0].bad, 1);
+        assert_eq!(days[0].average_duration, Some(2000));
+        assert_eq!(days[1].reviews, 1);
+        assert_eq!(days[1].accuracy, Some(1.0));
+        assert_eq!(days[1].average_duration, None);
+    }
+    #[test]
+    fn stats_plural() {
+        assert_eq!(format!("{} day{}", 0, plural(0)), "0 days");
+        assert_eq!(format!("{} day{}", 1, plural(1)), "1 day");
+        assert_eq!(format!("{} day{}", 2, plural(2)), "2 days");
+    }
+}
@@ -0,0 +1,207 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::error::Result;
+use crate::history::{self, Review};
+use crate::lock::{Conflict, Profile};
+use crate::session::{self, Merge, Session};
+
+pub struct SyncOpts {
+    pub profile: String,
+    /// Shows the current setup when none
+    pub dir: Option<PathBuf>,
+    /// Stops syncing the profile
+    pub off: bool,
+    pub conflict: Conflict,
+}
+
+/// The shared directory of each synced profile, on this machine
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SyncDirs(BTreeMap<String, PathBuf>);
+
+fn config_path() -> PathBuf {
+    session::data_dir().join("sync.json")
+}
+
+impl SyncDirs {
+    fn load() -> Result<SyncDirs> {
+        match fs::read_to_string(config_path()) {
+            Ok(json) => Ok(serde_json::from_str(&json)?),
+            Err(_) => Ok(SyncDirs::default()),
+        }
+    }
+
+    fn save(&self) -> Result<()> {
+        let path = config_path();
+        fs::create_dir_all(path.parent().unwrap())?;
+        fs::write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+}
+
+/// Names the log this machine writes, generated on first use
+fn machine() -> Result<String> {
+    let path = session::data_dir().join("machine");
+    if let Ok(id) = fs::read_to_string(&path) {
+        return Ok(id.trim().to_string());
+    }
+    let id = format!("{:016x}", rand::thread_rng().gen::<u64>());
+    fs::create_dir_all(session::data_dir())?;
+    fs::write(path, &id)?;
+    Ok(id)
+}
+
+fn logs_dir(dir: &Path, profile: &str) -> PathBuf {
+    dir.join(profile)
+}
+
+/// Every log of the profile in the shared directory, by machine
+fn read_logs(dir: &Path) -> Result<BTreeMap<String, Vec<Review>>> {
+    let mut logs = BTreeMap::new();
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return Ok(logs),
+    };
+    for entry in entries {
+        let path = entry?.path();
+        let machine = match path.file_stem().and_then(|stem| stem.to_str()) {
+            Some(machine) if path.extension().is_some_and(|ext| ext == "json") => machine,
+            _ => continue,
+        };
+        let log = serde_json::from_str(&fs::read_to_string(&path)?)?;
+        logs.insert(machine.to_string(), log);
+    }
+    Ok(logs)
+}
+
+/// Written aside then renamed, so that another machine never reads half a
+/// log
+fn write_log(dir: &Path, machine: &str, history: &[Review]) -> Result<()> {
+    fs::create_dir_all(dir)?;
+    let tmp = dir.join(format!(".{}.json.tmp", machine));
+    fs::write(&tmp, serde_json::to_string(history)?)?;
+    fs::rename(tmp, dir.join(format!("{}.json", machine)))?;
+    Ok(())
+}
+
+/// Adds the reviews of every log to `session`. When some were missing, the
+/// cards are scheduled again from the whole history, so that every machine
+/// ends up with the same state.
+fn merge(session: &mut Session, logs: BTreeMap<String, Vec<Review>>) {
+    let history = logs.values().fold(session.history.clone(), |history, log| {
+        history::merge(&history, log)
+    });
+    if history.len() > session.history.len() {
+        let mut other = Session::from(vec![]);
+        other.history = history;
+        session.merge(other, Merge::Replay);
+    }
+}
+
+/// Merges the logs of the shared directory, if the profile is synced
+pub(crate) fn pull(session: &mut Session, profile: &str) -> Result<()> {
+    if let Some(dir) = SyncDirs::load()?.0.get(profile) {
+        merge(session, read_logs(&logs_dir(dir, profile))?);
+    }
+    Ok(())
+}
+
+/// Writes the history as the log of this machine, if the profile is synced
+pub(crate) fn push(session: &Session, profile: &str) -> Result<()> {
+    if let Some(dir) = SyncDirs::load()?.0.get(profile) {
+        write_log(&logs_dir(dir, profile), &machine()?, &session.history)?;
+    }
+    Ok(())
+}
+
+pub fn sync(opts: SyncOpts) -> Result<()> {
+    let mut dirs = SyncDirs::load()?;
+    if opts.off {
+        dirs.0.remove(&opts.profile);
+        return dirs.save();
+    }
+    if let Some(dir) = opts.dir {
+        dirs.0.insert(opts.profile.clone(), fs::canonicalize(dir)?);
+        dirs.save()?;
+        // load pulls the other machines' reviews, save shares ours
+        let profile = Profile::open(&opts.profile, opts.conflict)?;
+        profile.save(&profile.load())?;
+    }
+
+    match dirs.0.get(&opts.profile) {
+        Some(dir) => {
+            println!("{} is synced through {}", opts.profile, dir.display());
+            let machine = machine()?;
+            for (id, log) in read_logs(&logs_dir(dir, &opts.profile))? {
+                let this = if id == machine { " (this machine)" } else { "" };
+                println!("{}{}: {} reviews", id, this, log.len());
+            }
+        }
+        None => println!("{} is not synced", opts.profile),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::card::{Card, Factors, Rating};
+
+    fn a_review(value: Factors, rating: Rating, time: u64) -> Review {
+        Review {
+            value: value.into(),
+            rating,
+            time,
+            duration: None,
+            input: None,
+        }
+    }
+
+    #[test]
+    fn sync_merge() {
+        let home = vec![
+            a_review(Factors(7, 8), Rating::Bad, 1),
+            a_review(Factors(6, 9), Rating::Good, 3),
+        ];
+        let school = vec![
+            a_review(Factors(7, 8), Rating::Bad, 1),
+            a_review(Factors(7, 8), Rating::Good, 2),
+        ];
+        let logs = |a: &[Review], b: &[Review]| {
+            let mut logs = BTreeMap::new();
+            logs.insert("a".to_string(), a.to_vec());
+            logs.insert("b".to_string(), b.to_vec());
+            logs
+        };
+
+        let mut one = Session::from(vec![Card::new(7, 8)]);
+        merge(&mut one, logs(&home, &school));
+        let mut other = Session::new();
+        merge(&mut other, logs(&school, &home));
+
+        assert_eq!(one.history.len(), 3);
+        assert_eq!(one.history, other.history);
+        assert_eq!(one.to_json().unwrap(), other.to_json().unwrap());
+
+        let mut synced = Session::replay(one.history.clone());
+        merge(&mut synced, logs(&home, &[]));
+        assert_eq!(synced.to_json().unwrap(), one.to_json().unwrap());
+    }
+
+    #[test]
+    fn sync_logs() {
+        let dir = std::env::temp_dir().join(format!("multa-sync-{}", std::process::id()));
+        let history = vec![a_review(Factors(7, 8), Rating::Good, 1)];
+        write_log(&dir, "home", &history).unwrap();
+        write_log(&dir, "school", &[]).unwrap();
+
+        let logs = read_logs(&dir).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(logs.keys().collect::<Vec<_>>(), ["home", "school"]);
+        assert_eq!(logs["home"], history);
+        assert!(read_logs(&dir).unwrap().is_empty());
+    }
+}
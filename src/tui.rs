@@ -0,0 +1,516 @@
+use chrono::{Local, NaiveDate};
+use crossterm::{
+    cursor,
+    event::{self, Event, KeyCode, KeyEvent, KeyModifiers},
+    execute, queue,
+    style::{self, Color},
+    terminal::{self, ClearType},
+};
+use rand::{thread_rng, Rng};
+use std::io::{stdout, Write};
+use std::mem;
+use std::time::{Duration, Instant};
+
+use crate::achievements::{self, Progress};
+use crate::card::{Card, Factors, Rating};
+use crate::duel::{Duel, Player};
+use crate::engine::{Action, Input, RatedCard, State, View};
+use crate::error::Result;
+use crate::ghost::{Ghost, Sprint};
+use crate::lock::{Conflict, Profile};
+use crate::practice::PracticeOpts;
+use crate::session::Session;
+
+pub struct Opts {
+    pub practice: PracticeOpts,
+    pub examination: bool,
+    /// Only practice leeches, with a hint for each card
+    pub remediation: bool,
+    /// End after this many cards, racing the best previous run
+    pub sprint: Option<usize>,
+}
+
+/// Number of questions of the daily challenge
+const DAILY_LENGTH: usize = 20;
+
+pub struct DailyOpts {
+    /// Where the answers are recorded
+    pub profile: String,
+    /// The challenge of this day, the same for everyone
+    pub date: NaiveDate,
+    pub conflict: Conflict,
+}
+
+/// Number of cards each player answers in a duel
+const DUEL_ROUNDS: usize = 10;
+
+pub struct DuelOpts {
+    /// One profile per player
+    pub profiles: Vec<String>,
+    /// Deal the cards from this seed instead of at random
+    pub seed: Option<u64>,
+    pub conflict: Conflict,
+}
+
+/// The seed of the daily challenge, e.g. 20240131
+fn daily_seed(date: NaiveDate) -> u64 {
+    date.format("%Y%m%d").to_string().parse().unwrap()
+}
+
+const IDLE_INTERVAL: Duration = Duration::from_millis(100);
+const RACE_WIDTH: usize = 30;
+
+struct Keyboard<'a> {
+    /// Called while waiting for a key, e.g. to animate the screen
+    idle: Option<&'a dyn Fn() -> Result<()>>,
+}
+
+impl Input for Keyboard<'_> {
+    fn read(&mut self, view: &View) -> Result<Action> {
+        let mut line = String::new();
+
+        loop {
+            if let Some(idle) = self.idle {
+                while !event::poll(IDLE_INTERVAL)? {
+                    idle()?;
+                }
+            }
+            let event = event::read()?;
+            match event {
+                Event::Key(KeyEvent {
+                    modifiers: KeyModifiers::CONTROL,
+                    code: KeyCode::Char('c'),
+                }) => return Ok(Action::Exit),
+
+                Event::Key(KeyEvent {
+                    code: KeyCode::Up, ..
+                })
+                | Event::Key(KeyEvent {
+                    code: KeyCode::Char('k'),
+                    ..
+                }) if view.can_undo => return Ok(Action::Undo),
+
+                Event::Key(KeyEvent {
+                    code: KeyCode::Right,
+                    ..
+                })
+                | Event::Key(KeyEvent {
+                    code: KeyCode::Char('l'),
+                    ..
+                }) if view.answer_visible => return Ok(Action::Review(Rating::Good)),
+
+                Event::Key(KeyEvent {
+                    code: KeyCode::Left,
+                    ..
+                })
+                | Event::Key(KeyEvent {
+                    code: KeyCode::Char('h'),
+                    ..
+                }) => return Ok(Action::Review(Rating::Bad)),
+
+                Event::Key(KeyEvent {
+                    code: KeyCode::Enter,
+                    ..
+                })
+                | Event::Key(KeyEvent {
+                    code: KeyCode::Char(' '),
+                    ..
+                }) if !line.is_empty() => return Ok(Action::Input(line)),
+
+                Event::Key(KeyEvent {
+                    code: KeyCode::Backspace,
+                    ..
+                }) if !line.is_empty() => {
+                    line.pop();
+                    execute!(
+                        stdout(),
+                        cursor::MoveLeft(1),
+                        terminal::Clear(ClearType::UntilNewLine)
+                    )?
+                }
+
+                // nothing to erase, which does not show the answer either
+                Event::Key(KeyEvent {
+                    code: KeyCode::Backspace,
+                    ..
+                }) => (),
+
+                Event::Key(KeyEvent {
+                    code: KeyCode::Char(c),
+                    ..
+                }) if c.is_ascii_digit() => {
+                    line.push(c);
+                    execute!(stdout(), style::Print(c.to_string()))?;
+                }
+
+                Event::Key(_) if !view.answer_visible => {
+                    return Ok(Action::ShowAnswer);
+                }
+
+                _ => (),
+            }
+        }
+    }
+}
+
+fn render(view: &View) -> Result<()> {
+    let mut stdout = stdout();
+
+    queue!(
+        &stdout,
+        cursor::MoveTo(0, 0),
+        terminal::Clear(ClearType::All),
+        style::ResetColor,
+    )?;
+
+    if let Some(rated) = &view.last_card {
+        match rated.rating {
+            Rating::Good => queue!(
+                &stdout,
+                style::Print(format!("{} = {}", rated.card.value, rated.answer)),
+                style::SetForegroundColor(Color::Green),
+                style::Print(" OK"),
+                style::ResetColor,
+                cursor::MoveToNextLine(1)
+            )?,
+            Rating::Bad => {
+                if let Some(input) = &rated.input {
+                    queue!(
+                        &stdout,
+                        style::Print(format!("{} != {}", rated.card.value, input)),
+                        style::SetForegroundColor(Color::Red),
+                        style::Print(" KO!!!"),
+                        style::ResetColor,
+                        style::Print(format!(" => {}", &rated.answer)),
+                        style::ResetColor,
+                        cursor::MoveToNextLine(1)
+                    )?
+                } else {
+                    queue!(
+                        &stdout,
+                        style::Print(format!("{} = {}", &rated.card.value, rated.answer)),
+                        style::SetForegroundColor(Color::Red),
+                        style::Print(" KO!!!"),
+                        style::ResetColor,
+                        cursor::MoveToNextLine(1)
+                    )?;
+                }
+            }
+        }
+    };
+
+    if let Some(hint) = &view.hint {
+        queue!(
+            &stdout,
+            style::SetForegroundColor(Color::DarkGrey),
+            style::Print(format!("{} = {}", hint, hint.compute())),
+            style::ResetColor,
+            cursor::MoveToNextLine(1)
+        )?;
+    }
+
+    if let Some(card) = &view.card {
+        queue!(&stdout, style::Print(format!("{} = ", card)))?;
+
+        if view.answer_visible {
+            queue!(&stdout, style::Print(card.answer()))?;
+        }
+    }
+
+    stdout.flush()?;
+
+    Ok(())
+}
+
+fn render_turn(duel: &Duel, profile: &str, card: Factors, round: &[RatedCard]) -> Result<()> {
+    let mut stdout = stdout();
+    queue!(
+        &stdout,
+        cursor::MoveTo(0, 0),
+        terminal::Clear(ClearType::All),
+    )?;
+
+    for (player, rated) in duel.players.iter().zip(round) {
+        let color = match rated.rating {
+            Rating::Good => Color::Green,
+            Rating::Bad => Color::Red,
+        };
+        queue!(
+            &stdout,
+            style::SetForegroundColor(color),
+            style::Print(format!("{}: {}", player.profile, rated)),
+            style::ResetColor,
+            cursor::MoveToNextLine(1)
+        )?;
+    }
+
+    queue!(
+        &stdout,
+        cursor::MoveToNextLine(1),
+        style::SetForegroundColor(Color::DarkGrey),
+        style::Print(format!("{}'s turn", profile)),
+        style::ResetColor,
+        cursor::MoveToNextLine(1),
+        style::Print(format!("{} = ", card)),
+    )?;
+    stdout.flush()?;
+    Ok(())
+}
+
+/// Asks the cards of the duel until they are all answered or someone exits
+fn take_turns(duel: &mut Duel) -> Result<()> {
+    terminal::enable_raw_mode()?;
+    let mut stdout = stdout();
+    execute!(stdout, terminal::EnterAlternateScreen)?;
+
+    let mut last_round = Vec::new();
+    let mut round = Vec::new();
+    let view = View {
+        last_card: None,
+        card: None,
+        hint: None,
+        answer_visible: true,
+        can_undo: false,
+    };
+    'duel: while let Some((player, card)) = duel.current() {
+        let shown_at = Instant::now();
+        let input = loop {
+            render_turn(duel, &player.profile, card, &last_round)?;
+            match (Keyboard { idle: None }).read(&view)? {
+                Action::Input(input) => break input,
+                Action::Exit => break 'duel,
+                _ => (),
+            }
+        };
+
+        round.extend(duel.answer(&input, shown_at.elapsed()));
+        if round.len() == duel.players.len() {
+            last_round = mem::take(&mut round);
+        }
+    }
+
+    execute!(stdout, terminal::LeaveAlternateScreen)?;
+    terminal::disable_raw_mode()?;
+    Ok(())
+}
+
+/// Hot seat: the players pass the keyboard around after each answer. The
+/// answers of a round are only shown once everyone has answered.
+pub fn duel(opts: DuelOpts) -> Result<()> {
+    let seed = opts.seed.unwrap_or_else(|| thread_rng().gen());
+    let profiles = opts
+        .profiles
+        .iter()
+        .map(|profile| Profile::open(profile, opts.conflict))
+        .collect::<Result<Vec<Profile>>>()?;
+    let players = profiles
+        .iter()
+        .map(|profile| Player::new(profile.name.clone(), profile.load()))
+        .collect();
+    let mut duel = Duel::new(players, Duel::deck(seed, DUEL_ROUNDS));
+
+    take_turns(&mut duel)?;
+    println!("{}", duel);
+    println!("Seed: {}", seed);
+
+    for (profile, player) in profiles.iter().zip(&duel.players) {
+        profile.save(&player.session)?;
+    }
+    Ok(())
+}
+
+/// A session of a fixed number of cards, against the ghost of the best
+/// previous run
+pub(crate) struct Race {
+    length: usize,
+    ghost: Option<Ghost>,
+}
+
+fn race_bar(label: &str, done: usize, length: usize) -> String {
+    let width = (done * RACE_WIDTH).checked_div(length).unwrap_or(0);
+    format!(
+        "{:6}{}{} {}/{}",
+        label,
+        "█".repeat(width),
+        "░".repeat(RACE_WIDTH - width),
+        done,
+        length
+    )
+}
+
+impl Race {
+    /// Draws the progress bars at the bottom of the screen
+    fn render(&self, state: &State) -> Result<()> {
+        let (_, rows) = terminal::size()?;
+        let summary = state.summary();
+        let mut stdout = stdout();
+
+        queue!(
+            &stdout,
+            cursor::SavePosition,
+            cursor::MoveTo(0, rows.saturating_sub(2)),
+            terminal::Clear(ClearType::CurrentLine),
+            style::Print(race_bar("You", summary.ok + summary.ko, self.length)),
+        )?;
+        if let Some(ghost) = &self.ghost {
+            let done = ghost.progress(state.elapsed()).min(self.length);
+            queue!(
+                &stdout,
+                cursor::MoveTo(0, rows.saturating_sub(1)),
+                terminal::Clear(ClearType::CurrentLine),
+                style::SetForegroundColor(Color::DarkGrey),
+                style::Print(race_bar("Ghost", done, self.length)),
+                style::ResetColor,
+            )?;
+        }
+        queue!(&stdout, cursor::RestorePosition)?;
+        stdout.flush()?;
+        Ok(())
+    }
+
+    /// The sprint, if every card was answered
+    fn sprint(&self, state: &State) -> Option<Sprint> {
+        let laps = state.laps();
+        Some(Sprint::new(laps)).filter(|_| !laps.is_empty() && laps.len() == self.length)
+    }
+
+    fn result(&self, state: &State) -> String {
+        let time = match state.laps().last() {
+            Some(time) => time.as_secs_f64(),
+            None => 0.0,
+        };
+        match &self.ghost {
+            _ if self.sprint(state).is_none() => "Sprint not finished".to_string(),
+            Some(ghost) => {
+                let best = ghost.time().as_secs_f64();
+                if time <= best {
+                    format!("New best! {:.1}s ahead of your ghost", best - time)
+                } else {
+                    format!("{:.1}s behind your ghost", time - best)
+                }
+            }
+            None => format!("{} cards in {:.1}s", self.length, time),
+        }
+    }
+}
+
+/// Runs `state` full screen until the session is over
+pub(crate) fn play(session: &mut Session, state: &mut State, race: Option<&Race>) -> Result<()> {
+    terminal::enable_raw_mode()?;
+    let mut stdout = stdout();
+    execute!(stdout, terminal::EnterAlternateScreen)?;
+
+    while !state.is_over() {
+        let view = state.view();
+        render(&view)?;
+
+        let action = match race {
+            Some(race) => {
+                let idle = || race.render(state);
+                idle()?;
+                Keyboard { idle: Some(&idle) }.read(&view)?
+            }
+            None => Keyboard { idle: None }.read(&view)?,
+        };
+        state.update(session, action);
+    }
+
+    execute!(stdout, terminal::LeaveAlternateScreen)?;
+    terminal::disable_raw_mode()?;
+    Ok(())
+}
+
+pub fn run(opts: Opts) -> Result<()> {
+    let (profile, mut session) = opts.practice.open()?;
+    if opts.remediation {
+        session.focus(Card::is_leech);
+        if session.peek().is_none() {
+            println!("No leeches to practice");
+            return Ok(());
+        }
+    }
+
+    let xp = achievements::xp(&session.history);
+    let race = opts.sprint.map(|length| Race {
+        length,
+        ghost: Ghost::best(&session.sprints, length),
+    });
+    let mut state = State::new(&session, opts.examination, opts.remediation);
+    if let Some(race) = &race {
+        state.limit(race.length);
+    }
+    play(&mut session, &mut state, race.as_ref())?;
+
+    execute!(
+        stdout(),
+        style::Print(state.summary()),
+        cursor::MoveToNextLine(1)
+    )?;
+    if let Some(race) = &race {
+        execute!(
+            stdout(),
+            style::Print(race.result(&state)),
+            cursor::MoveToNextLine(1)
+        )?;
+        session.sprints.extend(race.sprint(&state));
+    }
+    announce(&mut session, xp)?;
+    profile.save(&session)
+}
+
+/// XP earned since the player had `xp`, the streak and the new badges
+fn announce(session: &mut Session, xp: u64) -> Result<()> {
+    let progress = Progress::new(&session.history, Local::now().date_naive());
+    let mut stdout = stdout();
+    execute!(
+        stdout,
+        style::Print(format!(
+            "+{} XP ({} XP in all), streak: {} day(s)",
+            progress.xp - xp,
+            progress.xp,
+            progress.streak
+        )),
+        cursor::MoveToNextLine(1)
+    )?;
+    for badge in progress.unlock(session) {
+        execute!(
+            stdout,
+            style::SetForegroundColor(Color::Yellow),
+            style::Print(format!("New badge: {}", badge)),
+            style::ResetColor,
+            cursor::MoveToNextLine(1)
+        )?;
+    }
+    Ok(())
+}
+
+/// Cards shuffled from the date and asked in order whatever the answers, as
+/// in a duel of one, so that everyone gets the same questions on a given
+/// day. The answers are recorded in the profile.
+pub fn daily(opts: DailyOpts) -> Result<()> {
+    let profile = Profile::open(&opts.profile, opts.conflict)?;
+    let player = Player::new(profile.name.clone(), profile.load());
+    let deck = Duel::deck(daily_seed(opts.date), DAILY_LENGTH);
+    let mut duel = Duel::new(vec![player], deck);
+
+    take_turns(&mut duel)?;
+    let player = &duel.players[0];
+    println!(
+        "Daily challenge of {}: {} in {}s",
+        opts.date,
+        player.summary,
+        player.time.as_secs()
+    );
+    profile.save(&player.session)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn daily_seed_from_date() {
+        let date = NaiveDate::from_ymd_opt(2024, 1, 31).unwrap();
+        assert_eq!(daily_seed(date), 20240131);
+    }
+}
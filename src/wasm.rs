@@ -0,0 +1,86 @@
+use std::time::Duration;
+use wasm_bindgen::prelude::*;
+
+use crate::card::{Factors, Rating};
+use crate::error::ErrorKind;
+use crate::session::Session;
+
+fn rating(good: bool) -> Rating {
+    if good {
+        Rating::Good
+    } else {
+        Rating::Bad
+    }
+}
+
+fn js_error(err: ErrorKind) -> JsValue {
+    JsValue::from_str(&format!("{:?}", err))
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy)]
+pub struct WasmCard {
+    pub x: u8,
+    pub y: u8,
+    pub answer: u8,
+}
+
+/// A `Session` for JavaScript. `toJson` and `fromJson` use the format of the
+/// profile files, so that progress can move between the web and the terminal
+#[wasm_bindgen]
+pub struct WasmSession(Session);
+
+#[wasm_bindgen]
+impl WasmSession {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> WasmSession {
+        WasmSession(Session::new())
+    }
+
+    #[wasm_bindgen(js_name = fromJson)]
+    pub fn from_json(json: &str) -> Result<WasmSession, JsValue> {
+        Session::from_json(json).map(WasmSession).map_err(js_error)
+    }
+
+    #[wasm_bindgen(js_name = toJson)]
+    pub fn to_json(&self) -> Result<String, JsValue> {
+        self.0.to_json().map_err(js_error)
+    }
+
+    /// The card to answer, `undefined` when there is nothing to practice.
+    /// Only the times tables are practiced, custom decks stay aside.
+    pub fn peek(&self) -> Option<WasmCard> {
+        let Factors(x, y) = self.0.peek()?.value.factors()?;
+        Some(WasmCard {
+            x,
+            y,
+            answer: x * y,
+        })
+    }
+
+    pub fn review(&mut self, good: bool) {
+        self.0.review(rating(good))
+    }
+
+    /// Grades the typed `input`, answered in `elapsed_ms`. Returns whether
+    /// it was right
+    pub fn answer(&mut self, input: &str, elapsed_ms: f64) -> bool {
+        let good = match self.0.peek() {
+            Some(card) => input.trim() == card.value.answer(),
+            None => return false,
+        };
+        let elapsed = Duration::from_millis(elapsed_ms as u64);
+        self.0.review_answer(rating(good), input.trim(), elapsed);
+        good
+    }
+
+    pub fn rollback(&mut self) {
+        self.0.rollback()
+    }
+}
+
+impl Default for WasmSession {
+    fn default() -> WasmSession {
+        WasmSession::new()
+    }
+}
@@ -0,0 +1,137 @@
+use log::warn;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::io::{self, Cursor};
+use tiny_http::{Header, Method, Request as HttpRequest, Response as HttpResponse};
+
+use crate::error::{ErrorKind, Result};
+use crate::lock::{Conflict, Profile};
+use crate::serve::{Request, Response, Server};
+use crate::session;
+
+const PAGE: &str = include_str!("web.html");
+
+pub struct WebOpts {
+    pub port: u16,
+    pub conflict: Conflict,
+}
+
+fn header(value: &str) -> Header {
+    Header::from_bytes(&b"Content-Type"[..], value.as_bytes()).unwrap()
+}
+
+/// Other web pages cannot send JSON here without a preflight the server
+/// does not answer, so they cannot practice for a player
+fn is_json(headers: &[Header]) -> bool {
+    headers.iter().any(|header| {
+        header.field.equiv("Content-Type") && header.value.as_str().starts_with("application/json")
+    })
+}
+
+/// One `Server` per profile, so that several players can practice at once.
+/// The API is the `serve --stdio` protocol: POST a request to
+/// `/api/<profile>` and get the response back. Each profile is locked
+/// from its first request until the server stops.
+#[derive(Default)]
+pub struct Web {
+    servers: HashMap<String, Server>,
+    conflict: Conflict,
+}
+
+impl Web {
+    pub fn api(&mut self, profile: &str, body: &str) -> Response {
+        if let Err(ErrorKind::InvalidInput(message)) = session::check_name("profile", profile) {
+            return Response::Error { message };
+        }
+
+        let server = match self.servers.entry(profile.to_string()) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => match Profile::open(profile, self.conflict) {
+                Ok(opened) => entry.insert(Server::open(opened)),
+                Err(e) => {
+                    return Response::Error {
+                        message: format!("{:?}", e),
+                    }
+                }
+            },
+        };
+        match serde_json::from_str::<Request>(body) {
+            Ok(request) => server.handle(request),
+            Err(e) => Response::Error {
+                message: e.to_string(),
+            },
+        }
+    }
+}
+
+/// Errors only concern the request, the server keeps going
+fn respond(web: &mut Web, request: &mut HttpRequest) -> HttpResponse<Cursor<Vec<u8>>> {
+    let url = request.url().to_string();
+    match (request.method(), url.as_str()) {
+        (Method::Get, "/") => {
+            HttpResponse::from_string(PAGE).with_header(header("text/html; charset=utf-8"))
+        }
+        (Method::Post, path) if path.starts_with("/api/") => {
+            if !is_json(request.headers()) {
+                return HttpResponse::from_string("Expected application/json")
+                    .with_status_code(415);
+            }
+            let mut body = String::new();
+            if let Err(e) = request.as_reader().read_to_string(&mut body) {
+                return HttpResponse::from_string(e.to_string()).with_status_code(400);
+            }
+            let response = web.api(&path["/api/".len()..], &body);
+            match serde_json::to_string(&response) {
+                Ok(json) => HttpResponse::from_string(json).with_header(header("application/json")),
+                Err(e) => HttpResponse::from_string(e.to_string()).with_status_code(500),
+            }
+        }
+        _ => HttpResponse::from_string("Not found").with_status_code(404),
+    }
+}
+
+pub fn web(opts: WebOpts) -> Result<()> {
+    let address = ("127.0.0.1", opts.port);
+    let http = tiny_http::Server::http(address).map_err(|e| io::Error::other(e.to_string()))?;
+    println!("Listening on http://localhost:{}", opts.port);
+
+    let mut web = Web {
+        conflict: opts.conflict,
+        ..Web::default()
+    };
+    for mut request in http.incoming_requests() {
+        let response = respond(&mut web, &mut request);
+        if let Err(e) = request.respond(response) {
+            warn!("Cannot answer a request: {}", e);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn web_rejects_paths_as_profile() {
+        let mut web = Web::default();
+        for profile in ["", "..", "a/b", "%2e%2e"] {
+            assert!(matches!(
+                web.api(profile, r#"{"cmd": "next"}"#),
+                Response::Error { .. }
+            ));
+        }
+        assert!(web.servers.is_empty());
+    }
+
+    #[test]
+    fn web_requires_json() {
+        let header = |field: &str, value: &str| {
+            Header::from_bytes(field.as_bytes(), value.as_bytes()).unwrap()
+        };
+        assert!(is_json(&[header("content-type", "application/json")]));
+        assert!(!is_json(&[header("Content-Type", "text/plain")]));
+        assert!(!is_json(&[]));
+    }
+}
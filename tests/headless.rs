@@ -0,0 +1,74 @@
+use multa::{play, Card, Fact, Factors, Rating, Script, Session, State, Status};
+
+fn a_session() -> Session {
+    Session::from(vec![Card::new(7, 8), Card::new(6, 9)])
+}
+
+fn run(session: &mut Session, script: &str) -> String {
+    let mut state = State::new(session, false, false);
+    let mut out = Vec::new();
+    play(
+        session,
+        &mut state,
+        &mut Script::new(script.as_bytes()),
+        &mut out,
+    )
+    .unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn full_session() {
+    let mut session = a_session();
+    let first = session.peek().unwrap().value.clone();
+    let second = session.cards[1].value.clone();
+
+    let script = format!("{}\n# a comment\n\n12\n", first.answer());
+    let out = run(&mut session, &script);
+
+    assert_eq!(
+        out.lines().collect::<Vec<_>>(),
+        vec![
+            format!("{} = ?", first),
+            format!("{} = {} OK", first, first.answer()),
+            format!("{} = ?", second),
+            format!("{} != 12 KO => {}", second, second.answer()),
+            format!("{} = ?", first),
+            "Summary: 1 OK; 1 KO".to_string(),
+        ]
+    );
+    assert_eq!(session.history.len(), 2);
+    assert_eq!(session.history[1].input.as_deref(), Some("12"));
+}
+
+#[test]
+fn show_undo_and_rate() {
+    let mut session = a_session();
+    let first = session.peek().unwrap().value.clone();
+
+    let out = run(&mut session, "bad\nundo\ngood\nexit\n");
+
+    assert!(out.contains(&format!("{} = {} KO", first, first.answer())));
+    assert!(out.contains(&format!("{} = {}\n", first, first.answer())));
+    assert!(out.ends_with("Summary: 1 OK; 0 KO\n"));
+    assert_eq!(session.history.len(), 1);
+    assert_eq!(session.history[0].rating, Rating::Good);
+    let card = session.cards.iter().find(|c| c.value == first).unwrap();
+    assert_eq!(card.status, Status::Learned(55));
+}
+
+#[test]
+fn invalid_line() {
+    let mut session = Session::from(vec![Card::new(2, 2)]);
+    let mut state = State::new(&session, false, false);
+    let mut out = Vec::new();
+    let result = play(
+        &mut session,
+        &mut state,
+        &mut Script::new("two\n".as_bytes()),
+        &mut out,
+    );
+
+    assert!(result.is_err());
+    assert_eq!(session.peek().unwrap().value, Fact::from(Factors(2, 2)));
+}